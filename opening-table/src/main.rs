@@ -1,15 +1,25 @@
 use float_ord::FloatOrd;
 use node::Node;
+use pentarust::alpha_beta::tuning;
+use pentarust::alpha_beta::TranspositionTable;
+use pentarust::alpha_beta::Weights;
 use pentarust::best_move;
+use pentarust::game::perft;
+use pentarust::game::perft_canonical;
 use pentarust::game::Board;
+use pentarust::game::GameRecord;
 use pentarust::game::Outcome;
 use pentarust::game::Player;
 use rand::thread_rng;
 use rand::Rng;
 use rusqlite::OptionalExtension;
 use rusqlite::{Connection, NO_PARAMS};
+use std::error::Error;
+use std::fs;
 use std::io::stdout;
 use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
 use std::time::Duration;
 use structopt::StructOpt;
 
@@ -31,17 +41,63 @@ enum Options {
     },
     #[structopt(name = "main-line")]
     MainLine,
+    /// Counts the positions reachable from a board, to check move generation
+    #[structopt(name = "perft")]
+    Perft {
+        depth: u32,
+        /// Board to start from, such as `x......o............................ x`
+        #[structopt(long = "board")]
+        board: Option<Board>,
+        /// Counts distinct positions up to symmetry instead of action sequences
+        #[structopt(long = "canonical")]
+        canonical: bool,
+    },
+    /// Fits the evaluation weights to the results of self-play and recorded games
+    #[structopt(name = "tune")]
+    Tune {
+        /// Number of self-play games to play
+        #[structopt(long = "games", default_value = "1000")]
+        games: usize,
+        /// Number of random moves opening each self-play game
+        #[structopt(long = "random-moves", default_value = "6")]
+        random_moves: usize,
+        /// Depth of the searches of the self-play games
+        #[structopt(long = "depth", default_value = "2")]
+        depth: u32,
+        /// Recorded games to learn from as well, one game per file
+        #[structopt(long = "record", parse(from_os_str))]
+        records: Vec<PathBuf>,
+        /// Weights to start from, instead of the built-in ones
+        #[structopt(long = "weights", parse(from_os_str))]
+        weights: Option<PathBuf>,
+        /// File the tuned weights are written to, to be loaded with `PENTARUST_WEIGHTS`
+        #[structopt(long = "output", default_value = "weights.txt", parse(from_os_str))]
+        output: PathBuf,
+    },
 }
 
-fn main() -> rusqlite::Result<()> {
+fn main() -> Result<(), Box<dyn Error>> {
     let opt = Options::from_args();
-    let conn = Connection::open("opening-table.sqlite")?;
+    let open = || Connection::open("opening-table.sqlite");
 
     match opt {
-        Options::Init => init(&conn)?,
-        Options::Stats => stats(&conn)?,
-        Options::Generate { c } => generate(&conn, c)?,
-        Options::MainLine => main_line(&conn)?,
+        Options::Init => init(&open()?)?,
+        Options::Stats => stats(&open()?)?,
+        Options::Generate { c } => generate(&open()?, c)?,
+        Options::MainLine => main_line(&open()?)?,
+        Options::Perft {
+            depth,
+            board,
+            canonical,
+        } => run_perft(board.unwrap_or_default(), depth, canonical),
+        Options::Tune {
+            games,
+            random_moves,
+            depth,
+            records,
+            weights,
+            output,
+        } => tune(games, random_moves, depth, &records, weights, &output)?,
     };
 
     Ok(())
@@ -78,19 +134,23 @@ fn stats(conn: &Connection) -> rusqlite::Result<()> {
 
 /// Repeatedly expands the tree
 fn generate(conn: &Connection, c: f64) -> rusqlite::Result<()> {
+    // Every playout searches with the same table, cleared between games
+    let transpo_table = TranspositionTable::with_megabytes(32);
+
     loop {
-        expand(conn, Board::default(), c)?;
+        expand(conn, Board::default(), c, &transpo_table)?;
     }
 }
 
 /// Expands the tree once
-fn expand(conn: &Connection, state: Board, c: f64) -> rusqlite::Result<Node> {
+fn expand(
+    conn: &Connection,
+    state: Board,
+    c: f64,
+    transpo_table: &TranspositionTable,
+) -> rusqlite::Result<Node> {
     let mut node = Node::get(conn, state)?;
-    let mut children: Vec<Board> = state
-        .children(false)
-        .into_iter()
-        .map(|c| c.canonical())
-        .collect();
+    let mut children: Vec<Board> = state.distinct_moves().map(|(_, c)| c.canonical()).collect();
 
     children.sort_unstable();
     children.dedup();
@@ -117,7 +177,8 @@ fn expand(conn: &Connection, state: Board, c: f64) -> rusqlite::Result<Node> {
             .expect("Tried to expand a terminal state")
             .0;
 
-        child_nodes[best_child_index] = expand(conn, child_nodes[best_child_index].board, c)?;
+        child_nodes[best_child_index] =
+            expand(conn, child_nodes[best_child_index].board, c, transpo_table)?;
 
         child_nodes
     } else {
@@ -142,7 +203,7 @@ fn expand(conn: &Connection, state: Board, c: f64) -> rusqlite::Result<Node> {
                     };
 
                     for _ in 0..GAMES_PER_NEW_NODE {
-                        match play_game(child) {
+                        match play_game(child, transpo_table) {
                             Outcome::Player1Win => node.player1_wins += 1,
                             Outcome::Player2Win => node.player2_wins += 1,
                             Outcome::Draw => (),
@@ -175,13 +236,21 @@ fn expand(conn: &Connection, state: Board, c: f64) -> rusqlite::Result<Node> {
     Ok(node)
 }
 
-fn play_game(mut board: Board) -> Outcome {
+fn play_game(board: Board, transpo_table: &TranspositionTable) -> Outcome {
+    let mut game = GameRecord::from_position(board);
+    transpo_table.new_game();
+
     loop {
-        if let Some(outcome) = board.outcome() {
+        if let Some(outcome) = game.outcome() {
             return outcome;
         }
 
-        board = board.apply_action(best_move(board, Duration::from_millis(100)));
+        game.play(best_move(
+            game.board(),
+            Duration::from_millis(100),
+            transpo_table,
+        ))
+        .expect("the engine played an illegal move");
     }
 }
 
@@ -202,9 +271,8 @@ fn main_line(conn: &Connection) -> rusqlite::Result<()> {
 
         let mut children: Vec<Board> = node
             .board
-            .children(false)
-            .into_iter()
-            .map(|c| c.canonical())
+            .distinct_moves()
+            .map(|(_, c)| c.canonical())
             .collect();
 
         children.sort_unstable();
@@ -231,3 +299,71 @@ fn main_line(conn: &Connection) -> rusqlite::Result<()> {
 
     Ok(())
 }
+
+fn run_perft(board: Board, depth: u32, canonical: bool) {
+    println!("{:#}", board);
+    println!("depth nodes player1_wins player2_wins draws");
+    for d in 1..=depth {
+        let counts = if canonical {
+            perft_canonical(board, d)
+        } else {
+            perft(board, d)
+        };
+
+        println!(
+            "{} {} {} {} {}",
+            d, counts.nodes, counts.player1_wins, counts.player2_wins, counts.draws
+        );
+    }
+}
+
+/// Tunes the weights on self-play and recorded games, then writes them to the output file
+fn tune(
+    games: usize,
+    random_moves: usize,
+    depth: u32,
+    records: &[PathBuf],
+    weights: Option<PathBuf>,
+    output: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let weights = match weights {
+        Some(path) => fs::read_to_string(path)?.parse()?,
+        None => Weights::DEFAULT,
+    };
+    // The self-play games are played with the weights being tuned
+    weights.set_engine().expect("the weights are only set once");
+
+    let mut samples = Vec::new();
+    for path in records {
+        let game: GameRecord = fs::read_to_string(path)?.parse()?;
+        samples.extend(tuning::samples(&game));
+    }
+
+    let mut rng = thread_rng();
+    for i in 0..games {
+        let game = tuning::self_play(&mut rng, random_moves, depth);
+        samples.extend(tuning::samples(&game));
+
+        print!("\rPlayed {}/{} games", i + 1, games);
+        stdout().flush()?;
+    }
+    println!();
+    println!("{} positions", samples.len());
+
+    let scale = tuning::fit_scale(&weights, &samples);
+    println!(
+        "scale {} loss {}",
+        scale,
+        tuning::log_loss(&weights, &samples, scale)
+    );
+
+    let weights = tuning::tune(weights, &samples, scale, |weights, loss| {
+        println!("loss {}", loss);
+        print!("{}", weights);
+    });
+
+    fs::write(output, weights.to_string())?;
+    println!("Wrote the weights to {}", output.display());
+
+    Ok(())
+}
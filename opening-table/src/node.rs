@@ -18,7 +18,7 @@ impl Node {
             "INSERT INTO node
             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             &[
-                &(node.board.player1 as i64) as &ToSql,
+                &(node.board.player1 as i64) as &dyn ToSql,
                 &(node.board.player2 as i64),
                 &(node.board.player1 | node.board.player2).count_ones(),
                 &node.games_played,
@@ -36,7 +36,7 @@ impl Node {
             "SELECT *
             FROM node
             WHERE player1=?1 AND PLAYER2=?2",
-            &[board.player1 as i64, board.player2 as i64],
+            [board.player1 as i64, board.player2 as i64],
             |row| {
                 Ok(Node {
                     board: Board {
@@ -58,7 +58,7 @@ impl Node {
             SET games_played=?3, player1_wins=?4, player2_wins=?5, expanded=?6
             WHERE player1=?1 AND PLAYER2=?2",
             &[
-                &(self.board.player1 as i64) as &ToSql,
+                &(self.board.player1 as i64) as &dyn ToSql,
                 &(self.board.player2 as i64),
                 &self.games_played,
                 &self.player1_wins,
@@ -4,35 +4,29 @@ use criterion::criterion_main;
 use criterion::BatchSize;
 use criterion::Benchmark;
 use criterion::Criterion;
-use pentarust::alpha_beta::negamax;
+use pentarust::alpha_beta::search_to_depth;
 use pentarust::alpha_beta::TranspositionTable;
 use pentarust::game::Board;
+use pentarust::game::Position;
+
+fn bench_search(c: &mut Criterion, name: &str, board: Board, depth: u32) {
+    let position = Position::from(board);
+
+    // Criterion only measures time, so print the size of the tree to compare search changes
+    let result = search_to_depth(position, depth, &TranspositionTable::new(1_000_000));
+    println!(
+        "{}: {} nodes, {:.1}% first move cutoffs",
+        name,
+        result.nodes,
+        result.first_move_cutoff_rate() * 100.
+    );
 
-fn alpha_beta_benchmark(c: &mut Criterion) {
     c.bench(
         "alpha-beta",
-        Benchmark::new("alpha-beta from root, depth 3+4", |b| {
+        Benchmark::new(name, move |b| {
             b.iter_batched_ref(
                 || TranspositionTable::new(1_000_000),
-                |transpo_table| {
-                    let board = Board::default();
-                    black_box(negamax(
-                        board,
-                        3,
-                        -1_000_000,
-                        1_000_000,
-                        transpo_table,
-                        &|| false,
-                    ));
-                    black_box(negamax(
-                        board,
-                        4,
-                        -1_000_000,
-                        1_000_000,
-                        transpo_table,
-                        &|| false,
-                    ));
-                },
+                |transpo_table| black_box(search_to_depth(position, depth, transpo_table)),
                 BatchSize::PerIteration,
             )
         })
@@ -40,5 +34,15 @@ fn alpha_beta_benchmark(c: &mut Criterion) {
     );
 }
 
+fn alpha_beta_benchmark(c: &mut Criterion) {
+    bench_search(c, "alpha-beta from root, depth 3+4", Board::default(), 4);
+    bench_search(
+        c,
+        "alpha-beta midgame, depth 3+4",
+        "x.o...o..x....x..............o..x..o x".parse().unwrap(),
+        4,
+    );
+}
+
 criterion_group!(benches, alpha_beta_benchmark);
 criterion_main!(benches);
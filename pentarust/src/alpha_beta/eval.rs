@@ -1,18 +1,116 @@
+use crate::alpha_beta::Weights;
+use crate::engine::Game;
+use crate::game::Action;
 use crate::game::Board;
-use crate::game::Player;
+use crate::game::Position;
+use crate::game::Swap;
+
+/// A game whose states can be evaluated by the alpha-beta search
+pub trait Evaluate: Game {
+    /// Returns a heuristic value of the state, from the perspective of the player to move
+    fn eval(self) -> i32;
+
+    /// Returns the moves that must be searched before the state can be evaluated, or None if the
+    /// state is quiet
+    ///
+    /// The search keeps playing these moves past its depth limit, so that threats just beyond the
+    /// horizon aren't missed. Every state is quiet by default.
+    fn forcing_moves(self) -> Option<Vec<(Self::Move, Self)>> {
+        None
+    }
+}
+
+impl Evaluate for Position {
+    fn eval(self) -> i32 {
+        Weights::engine().eval(self)
+    }
+
+    /// Forcing moves are the moves that win on the spot, or else, when the opponent threatens to
+    /// win and no swap alone stops it, the moves that block its threats
+    fn forcing_moves(self) -> Option<Vec<(Action, Position)>> {
+        let board = self.board();
+        let player = self.turn();
+
+        if board.may_win(player) {
+            if let Some(action) = board.winning_actions(player).next() {
+                return Some(vec![(action, self.apply_action(action))]);
+            }
+        }
+
+        let opponent = player.opponent();
+        if !board.may_win(opponent) || board.winning_actions(opponent).next().is_none() {
+            return None;
+        }
+
+        let mut moves = Vec::new();
+        for swap in Swap::iterator() {
+            let mut squares = board.defending_squares(player, *swap);
+
+            // When a swap stops the threats wherever the marble goes, the player isn't forced
+            if squares == board.free_squares() {
+                return None;
+            }
+
+            while squares != 0 {
+                let action = Action::new(squares.trailing_zeros() as u8, *swap);
+                squares &= squares - 1;
+                moves.push((action, self.apply_action(action)));
+            }
+        }
+
+        // When nothing stops the threats, any move shows the loss
+        if moves.is_empty() {
+            let (action, _) = board.moves().next()?;
+            moves.push((action, self.apply_action(action)));
+        }
+
+        Some(moves)
+    }
+}
 
 pub fn eval(board: Board) -> i32 {
-    let mut eval = 0;
+    Position::from(board).eval()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Outcome;
+    use crate::game::Player;
+
+    #[test]
+    fn forcing_moves_test() {
+        // Quiet
+        let board: Board = "x.o...o..x....x..............o..x..o x".parse().unwrap();
+        assert_eq!(Position::from(board).forcing_moves(), None);
+
+        // x wins by playing a win
+        let board: Board = "xxxx..oooo.......................... x".parse().unwrap();
+        let moves = Position::from(board).forcing_moves().unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].1.outcome(), Some(Outcome::Player1Win));
 
-    const CENTER_BONUS: i32 = 100;
-    const CENTERS: u64 = 0b000000_010010_000000_000000_010010_000000;
+        // o must stop x from completing the first row
+        let board: Board = "xxxx..ooo........................... o".parse().unwrap();
+        let moves = Position::from(board).forcing_moves().unwrap();
+        assert!(moves.iter().any(|(action, _)| action.square == 4));
+        for (_, child) in moves {
+            assert_eq!(child.board().winning_actions(Player::Player1).next(), None);
+        }
 
-    eval += (board.player1 & CENTERS).count_ones() as i32 * CENTER_BONUS;
-    eval -= (board.player2 & CENTERS).count_ones() as i32 * CENTER_BONUS;
+        // A swap that defends from a few squares still forces the player to pick one of them
+        let board: Board = "xxx.oxx.oxo...o.xooo.ox..oxoxx...oox x".parse().unwrap();
+        let moves = Position::from(board).forcing_moves().unwrap();
+        let actions: Vec<Action> = moves.iter().map(|&(action, _)| action).collect();
+        assert_eq!(actions, board.defending_actions(Player::Player1));
+        assert!(actions.iter().any(|action| board
+            .defending_squares(Player::Player1, action.swap)
+            .count_ones()
+            > 1));
 
-    if board.turn() == Player::Player1 {
-        eval
-    } else {
-        -eval
+        // o can't stop both open fours
+        let board: Board = "xxxx..o.o.o..xxxx..o.o.o......o..... o".parse().unwrap();
+        let moves = Position::from(board).forcing_moves().unwrap();
+        assert_eq!(moves.len(), 1);
     }
 }
@@ -1,7 +1,10 @@
 mod eval;
+mod pattern;
 mod search;
 mod transposition_table;
+pub mod tuning;
 
 pub use eval::*;
+pub use pattern::*;
 pub use search::*;
 pub use transposition_table::*;
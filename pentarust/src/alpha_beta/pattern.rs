@@ -0,0 +1,280 @@
+use crate::game::Position;
+use crate::game::LINES;
+use crate::game::SWAPPED_LINES;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const CENTERS: u64 = 0b000000_010010_000000_000000_010010_000000;
+
+static ENGINE_WEIGHTS: OnceLock<Weights> = OnceLock::new();
+
+/// The weights of the pattern evaluation
+///
+/// Every one of the 32 lines of five is scored for each player by the number of their marbles on
+/// it, as long as the opponent has none there. A line gets a second, smaller score when a single
+/// swap would bring more of the player's marbles onto it, since every action ends with a swap.
+///
+/// Weights are written as one line per field, which is the format of the weights files written by
+/// the tuner:
+///
+/// ```text
+/// lines 0 2 10 40 150 0
+/// swapped_lines 0 0 4 20 80 0
+/// center 20
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Weights {
+    /// Value of a line free of opponent marbles, indexed by the number of the player's marbles on it
+    pub lines: [i32; 6],
+    /// Value of a line that a swap would fill with more of the player's marbles, indexed by the
+    /// number of marbles it would then hold
+    pub swapped_lines: [i32; 6],
+    /// Value of a marble on the center of a quadrant, which is never moved by a swap
+    pub center: i32,
+}
+
+impl Weights {
+    /// The hand-picked weights, used by the engine unless others are set
+    pub const DEFAULT: Weights = Weights {
+        lines: [0, 2, 10, 40, 150, 0],
+        swapped_lines: [0, 0, 4, 20, 80, 0],
+        center: 20,
+    };
+
+    /// Number of weights, as seen by the tuner
+    pub(crate) const PARAMETERS: usize = 13;
+
+    /// Returns the weights used to evaluate the positions of the search
+    ///
+    /// These are the weights set with [`Weights::set_engine`], or [`Weights::DEFAULT`].
+    pub fn engine() -> &'static Weights {
+        ENGINE_WEIGHTS.get().unwrap_or(&Weights::DEFAULT)
+    }
+
+    /// Makes the search evaluate positions with these weights
+    ///
+    /// The engine weights can only be set once, so the weights are given back if they already were.
+    pub fn set_engine(self) -> Result<(), Weights> {
+        ENGINE_WEIGHTS.set(self)
+    }
+
+    /// Returns the weights as a flat list, in the order they are written
+    pub(crate) fn parameters(&self) -> [i32; Weights::PARAMETERS] {
+        let mut parameters = [0; Weights::PARAMETERS];
+        parameters[..6].copy_from_slice(&self.lines);
+        parameters[6..12].copy_from_slice(&self.swapped_lines);
+        parameters[12] = self.center;
+        parameters
+    }
+
+    /// Creates weights from a flat list, in the order they are written
+    pub(crate) fn from_parameters(parameters: [i32; Weights::PARAMETERS]) -> Weights {
+        let mut weights = Weights {
+            lines: [0; 6],
+            swapped_lines: [0; 6],
+            center: parameters[12],
+        };
+        weights.lines.copy_from_slice(&parameters[..6]);
+        weights.swapped_lines.copy_from_slice(&parameters[6..12]);
+        weights
+    }
+
+    /// Returns the value of the position, from the perspective of the player to move
+    pub fn eval(&self, position: Position) -> i32 {
+        let to_move = position.to_move();
+        let opponent = position.opponent();
+        self.player_value(to_move, opponent) - self.player_value(opponent, to_move)
+    }
+
+    /// Returns the value of the patterns of one player's marbles
+    fn player_value(&self, player: u64, opponent: u64) -> i32 {
+        let mut value = (player & CENTERS).count_ones() as i32 * self.center;
+
+        // One more than the most marbles a swap brings onto each line, or 0 if no swap leaves it
+        // free of opponent marbles
+        let mut swapped = [0; 32];
+        for swapped_lines in SWAPPED_LINES.chunks_exact(LINES.len()) {
+            for (swapped, &line) in swapped.iter_mut().zip(swapped_lines) {
+                // Without branches, since whether a line is open is unpredictable
+                let open = (line & opponent == 0) as u32;
+                *swapped = (*swapped).max(open * ((line & player).count_ones() + 1));
+            }
+        }
+
+        for (&line, &swapped) in LINES.iter().zip(&swapped) {
+            let open = (line & opponent == 0) as u32;
+            let count = (line & player).count_ones();
+            value += open as i32 * self.lines[count as usize];
+
+            let count = open * (count + 1);
+
+            if swapped > count {
+                value += self.swapped_lines[swapped as usize - 1];
+            }
+        }
+
+        value
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights::DEFAULT
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields: [(&str, &[i32]); 3] = [
+            ("lines", &self.lines),
+            ("swapped_lines", &self.swapped_lines),
+            ("center", &[self.center]),
+        ];
+
+        for (name, values) in fields.iter() {
+            write!(f, "{}", name)?;
+            for value in values.iter() {
+                write!(f, " {}", value)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Weights {
+    type Err = ParseWeightsError;
+
+    fn from_str(s: &str) -> Result<Weights, ParseWeightsError> {
+        let mut lines = None;
+        let mut swapped_lines = None;
+        let mut center = None;
+
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let invalid = || ParseWeightsError::InvalidLine(line.to_owned());
+            let mut tokens = line.split_whitespace();
+            let name = tokens.next().expect("empty lines are filtered out");
+            let values = tokens
+                .map(str::parse)
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_| invalid())?;
+
+            match (name, values.len()) {
+                ("lines", 6) if lines.is_none() => lines = Some(values),
+                ("swapped_lines", 6) if swapped_lines.is_none() => swapped_lines = Some(values),
+                ("center", 1) if center.is_none() => center = Some(values[0]),
+                _ => return Err(invalid()),
+            }
+        }
+
+        let mut weights = Weights {
+            lines: [0; 6],
+            swapped_lines: [0; 6],
+            center: center.ok_or(ParseWeightsError::MissingField("center"))?,
+        };
+        weights
+            .lines
+            .copy_from_slice(&lines.ok_or(ParseWeightsError::MissingField("lines"))?);
+        weights.swapped_lines.copy_from_slice(
+            &swapped_lines.ok_or(ParseWeightsError::MissingField("swapped_lines"))?,
+        );
+        Ok(weights)
+    }
+}
+
+/// An error returned when parsing weights fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseWeightsError {
+    InvalidLine(String),
+    MissingField(&'static str),
+}
+
+impl fmt::Display for ParseWeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseWeightsError::InvalidLine(line) => write!(f, "invalid line: {:?}", line),
+            ParseWeightsError::MissingField(name) => write!(f, "missing weights: {}", name),
+        }
+    }
+}
+
+impl Error for ParseWeightsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Board;
+
+    fn eval(board: &str) -> i32 {
+        let board: Board = board.parse().unwrap();
+        Weights::DEFAULT.eval(Position::from(board))
+    }
+
+    #[test]
+    fn eval_test() {
+        assert_eq!(eval(".................................... x"), 0);
+
+        // The same marbles, seen by either player
+        assert_eq!(
+            eval("xx....oo............................ x"),
+            -eval("oo....xx............................ x")
+        );
+
+        // Three in a row beats three scattered marbles
+        assert!(
+            eval("xxx...o...o...o..................... x")
+                > eval("x.....o.x.o...o...x................. x")
+        );
+
+        // The first row is blocked for both players, leaving x two lines and o three
+        let weights = Weights {
+            lines: [0, 1, 1, 1, 1, 1],
+            swapped_lines: [0; 6],
+            center: 0,
+        };
+        let board: Board = "xo.................................. x".parse().unwrap();
+        assert_eq!(weights.eval(Position::from(board)), 2 - 3);
+
+        // x completes the first row by swapping the top left and bottom left quadrants, and the
+        // fourth row by swapping the top right and bottom right quadrants
+        let weights = Weights {
+            lines: [0; 6],
+            swapped_lines: [0, 0, 0, 0, 0, 1],
+            center: 0,
+        };
+        let board: Board = "...xx.o.o.o.o.o...xxx............... x".parse().unwrap();
+        assert_eq!(weights.eval(Position::from(board)), 2);
+    }
+    #[test]
+    fn parameters_test() {
+        let parameters = Weights::DEFAULT.parameters();
+        assert_eq!(parameters[12], Weights::DEFAULT.center);
+        assert_eq!(Weights::from_parameters(parameters), Weights::DEFAULT);
+    }
+
+    #[test]
+    fn parse_test() {
+        let text = "lines 0 2 10 40 150 0\nswapped_lines 0 0 4 20 80 0\ncenter 20\n";
+        assert_eq!(Weights::DEFAULT.to_string(), text);
+        assert_eq!(text.parse(), Ok(Weights::DEFAULT));
+
+        assert_eq!(
+            "lines 0 2 10 40 150 0\ncenter 20".parse::<Weights>(),
+            Err(ParseWeightsError::MissingField("swapped_lines"))
+        );
+        assert_eq!(
+            "lines 0 2 10 40 150\n".parse::<Weights>(),
+            Err(ParseWeightsError::InvalidLine(
+                "lines 0 2 10 40 150".to_owned()
+            ))
+        );
+        assert_eq!(
+            "center twenty".parse::<Weights>(),
+            Err(ParseWeightsError::InvalidLine("center twenty".to_owned()))
+        );
+    }
+}
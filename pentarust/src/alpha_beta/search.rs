@@ -1,156 +1,731 @@
-use crate::alpha_beta::eval;
+use crate::alpha_beta::Bound;
+use crate::alpha_beta::Evaluate;
 use crate::alpha_beta::TranspositionTable;
-use crate::game::Action;
-use crate::game::Board;
+use crate::engine::Game;
 use crate::game::Outcome;
 use crate::game::Player;
-use crate::game::Swap;
 use rand::thread_rng;
 use rand::Rng;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread;
 use std::time::Duration;
 use std::time::SystemTime;
 
-pub fn search(board: Board, duration: Duration, transpo_table: &mut TranspositionTable) -> Action {
-    if board == Board::default() {
-        return Action::new(7, Swap::BL_BR);
+/// Score of a win on the empty board. Wins are scored `WIN_SCORE - ply`, so faster wins are better.
+pub const WIN_SCORE: i32 = 1_000_000;
+
+/// Scores above this value are forced wins, scores below its opposite are forced losses
+const PROVEN_SCORE: i32 = WIN_SCORE - 1_000;
+
+/// A forced result, in plies from the searched position
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Proof {
+    Win(u32),
+    Loss(u32),
+}
+
+impl Proof {
+    /// Returns the proof given by the score of a state at the given ply, if any
+    pub fn from_score(score: i32, ply: u32) -> Option<Proof> {
+        if score > PROVEN_SCORE {
+            Some(Proof::Win((WIN_SCORE - score) as u32 - ply))
+        } else if score < -PROVEN_SCORE {
+            Some(Proof::Loss((WIN_SCORE + score) as u32 - ply))
+        } else {
+            None
+        }
+    }
+}
+
+/// What a search found
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SearchResult<M> {
+    pub best_move: M,
+    /// Score of the best move, from the perspective of the player to move
+    pub score: i32,
+    /// Set when the score is a forced win or loss
+    pub proof: Option<Proof>,
+    /// Depth of the last iteration that was completed, not counting the root move
+    pub depth: u32,
+    /// Number of states visited by `negamax`
+    pub nodes: u64,
+    /// Number of states visited past the horizon by the quiescence search
+    pub quiescence_nodes: u64,
+    /// Number of states where a move failed high, which ends the search of the state
+    pub cutoffs: u64,
+    /// Number of cutoffs caused by the first move searched, a measure of the move ordering
+    pub first_move_cutoffs: u64,
+    pub elapsed: Duration,
+    /// The line the search expects to be played, starting with the best move
+    pub principal_variation: Vec<M>,
+    /// Scores of the moves at the root, best first. Only the score of the best move is exact, the
+    /// other scores are upper bounds, or come from the previous iteration when a win cut the
+    /// iteration short.
+    pub root_scores: Vec<(M, i32)>,
+}
+
+impl<M> SearchResult<M> {
+    /// Returns the proportion of cutoffs caused by the first move, 1 for a perfect ordering
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        if self.cutoffs == 0 {
+            return 0.;
+        }
+
+        self.first_move_cutoffs as f64 / self.cutoffs as f64
+    }
+}
+
+/// Depth of the first iteration of iterative deepening
+const START_DEPTH: u32 = 3;
+
+/// Root scores at or above this value end the search, since any guaranteed win is good
+const WIN_BETA: i32 = WIN_SCORE - 50;
+
+/// Initial distance between the previous score and the bounds of the aspiration window
+const ASPIRATION_WINDOW: i32 = 50;
+
+/// Number of killer moves kept per ply
+const KILLERS: usize = 2;
+
+/// Maximum number of states the quiescence search visits from one state at the horizon
+const QUIESCENCE_BUDGET: u32 = 64;
+
+/// Searches the state with iterative deepening until the time is up
+///
+/// # Panics
+///
+/// Panics if the game is over, or if the state has no legal moves.
+pub fn search<G: Evaluate>(
+    state: G,
+    duration: Duration,
+    transpo_table: &TranspositionTable<G::Move>,
+) -> SearchResult<G::Move> {
+    search_with_progress(state, duration, transpo_table, |_| {})
+}
+
+/// Same as [`search`], but calls `progress` with the result of every completed iteration
+///
+/// # Panics
+///
+/// Panics if the game is over, or if the state has no legal moves.
+pub fn search_with_progress<G, P>(
+    state: G,
+    duration: Duration,
+    transpo_table: &TranspositionTable<G::Move>,
+    progress: P,
+) -> SearchResult<G::Move>
+where
+    G: Evaluate,
+    P: FnMut(&SearchResult<G::Move>),
+{
+    transpo_table.new_search();
+    let stop = AtomicBool::new(false);
+    iterative_deepening(
+        state,
+        Some(duration),
+        None,
+        transpo_table,
+        &stop,
+        0,
+        progress,
+    )
+}
+
+/// Same as [`search_with_progress`], with `threads` threads searching the state at the same time
+///
+/// The helper threads run their own iterative deepening, starting at other depths and trying the
+/// moves in other orders, and share what they find through the transposition table (Lazy SMP). The
+/// result is the one of the main thread, with the nodes visited by all the threads. With a single
+/// thread, no thread is started and the search is the same as [`search_with_progress`].
+///
+/// # Panics
+///
+/// Panics if the game is over, or if the state has no legal moves.
+pub fn search_parallel<G, P>(
+    state: G,
+    duration: Duration,
+    threads: usize,
+    transpo_table: &TranspositionTable<G::Move>,
+    progress: P,
+) -> SearchResult<G::Move>
+where
+    G: Evaluate + Send,
+    G::Move: Send,
+    P: FnMut(&SearchResult<G::Move>),
+{
+    if threads <= 1 {
+        return search_with_progress(state, duration, transpo_table, progress);
     }
 
+    transpo_table.new_search();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..threads)
+            .map(|thread| {
+                let stop = &stop;
+                scope.spawn(move || {
+                    let duration = Some(duration);
+                    iterative_deepening(state, duration, None, transpo_table, stop, thread, |_| {})
+                        .nodes
+                })
+            })
+            .collect();
+
+        let mut result = iterative_deepening(
+            state,
+            Some(duration),
+            None,
+            transpo_table,
+            &stop,
+            0,
+            progress,
+        );
+
+        stop.store(true, Ordering::Relaxed);
+        for helper in helpers {
+            result.nodes += helper.join().expect("a search thread panicked");
+        }
+
+        result
+    })
+}
+
+/// Searches the state with iterative deepening up to the given depth, however long it takes
+///
+/// Unlike timed searches, the result only depends on the state and the transposition table, which
+/// makes it useful to compare versions of the search.
+///
+/// # Panics
+///
+/// Panics if the game is over, or if the state has no legal moves.
+pub fn search_to_depth<G: Evaluate>(
+    state: G,
+    depth: u32,
+    transpo_table: &TranspositionTable<G::Move>,
+) -> SearchResult<G::Move> {
+    transpo_table.new_search();
+    let stop = AtomicBool::new(false);
+    iterative_deepening(state, None, Some(depth), transpo_table, &stop, 0, |_| {})
+}
+
+/// Runs the iterative deepening of one thread, until the time is up, `max_depth` is reached or
+/// `stop` is set
+///
+/// Thread 0 is the main thread. The other threads start one ply deeper every other thread and try
+/// the moves in another order at first, so they don't all search the same tree.
+fn iterative_deepening<G, P>(
+    state: G,
+    duration: Option<Duration>,
+    max_depth: Option<u32>,
+    transpo_table: &TranspositionTable<G::Move>,
+    stop: &AtomicBool,
+    thread: usize,
+    mut progress: P,
+) -> SearchResult<G::Move>
+where
+    G: Evaluate,
+    P: FnMut(&SearchResult<G::Move>),
+{
     let start = SystemTime::now();
-    let mut depth = 3;
-    let mut children: Vec<(Board, i32)> =
-        board.children(false).into_iter().map(|c| (c, 0)).collect();
+    let elapsed = || start.elapsed().unwrap_or(Duration::from_secs(0));
+    let early_stop =
+        || stop.load(Ordering::Relaxed) || duration.is_some_and(|duration| elapsed() > duration);
 
-    loop {
-        let best_action = board.action_to(children[0].0);
-        let best_eval = children[0].1;
+    assert!(
+        state.outcome().is_none(),
+        "can't search a finished game: {:?}",
+        state
+    );
 
-        let mut alpha = -1_000_000;
-        let beta = 999_950; // Any guaranteed win is good
+    let mut searcher = Searcher::new(transpo_table, &early_stop);
 
-        for c in children.iter_mut() {
-            let early_stop = || start.elapsed().unwrap_or(Duration::from_secs(0)) > duration;
+    let mut depth = max_depth.map_or(START_DEPTH, |max| max.min(START_DEPTH));
+    depth += thread as u32 % 2;
+    let mut children: Vec<(G::Move, G, i32)> = state
+        .legal_moves()
+        .map(|(action, child)| (action, child, 0))
+        .collect();
 
-            let search_result = negamax(c.0, depth, alpha, beta, transpo_table, &early_stop);
-            let score = if let Some(value) = search_result {
-                -value
-            } else {
-                println!("Pentarust: searched depth {}", depth);
-                println!("Pentarust: eval {}", best_eval);
-                return best_action;
-            };
+    // Try the moves that win on the spot first, the search stops at the first win it finds
+    children.sort_by_cached_key(|(_, child, _)| match child.outcome() {
+        Some(outcome) => outcome_score(*child, outcome),
+        None => 0,
+    });
+    assert!(
+        !children.is_empty(),
+        "no legal moves to search: {:?}",
+        state
+    );
+    let len = children.len();
+    children.rotate_left(thread % len);
 
-            if score >= beta {
-                println!(
-                    "Pentarust: Found a guaranteed win at depth < {} !",
-                    depth + 1
-                );
-                return board.action_to(c.0);
-            }
+    let mut result = SearchResult {
+        best_move: children[0].0,
+        score: 0,
+        proof: None,
+        depth: 0,
+        nodes: 0,
+        quiescence_nodes: 0,
+        cutoffs: 0,
+        first_move_cutoffs: 0,
+        elapsed: Duration::from_secs(0),
+        principal_variation: vec![children[0].0],
+        root_scores: Vec::new(),
+    };
 
-            if score > alpha {
-                alpha = score
+    loop {
+        // Search with a window centred on the previous score, and widen the side it fails on
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = if result.depth > 0 && result.proof.is_none() {
+            (result.score - delta, (result.score + delta).min(WIN_BETA))
+        } else {
+            (-WIN_SCORE, WIN_BETA)
+        };
+
+        let won = loop {
+            let score = match searcher.search_root(&mut children, depth, alpha, beta) {
+                Some(score) => score,
+                None => {
+                    result.nodes = searcher.nodes;
+                    result.quiescence_nodes = searcher.quiescence_nodes;
+                    result.cutoffs = searcher.cutoffs;
+                    result.first_move_cutoffs = searcher.first_move_cutoffs;
+                    result.elapsed = elapsed();
+                    return result;
+                }
+            };
+
+            if score <= alpha && alpha > -WIN_SCORE {
+                alpha = (alpha - delta).max(-WIN_SCORE);
+            } else if score >= beta && beta < WIN_BETA {
+                beta = (beta + delta).min(WIN_BETA);
+            } else {
+                break score >= beta;
             }
+            delta *= 4;
+        };
 
-            c.1 = score;
+        if !won {
+            // Timed searches vary the order of close moves, so the engine doesn't always play the
+            // same game
+            let mut rng = thread_rng();
+            let noise = duration.is_some();
+            children.sort_by_cached_key(|(_action, _c, eval)| {
+                if noise {
+                    -eval + rng.gen_range(-10, 10)
+                } else {
+                    -eval
+                }
+            });
         }
 
-        let mut rng = thread_rng();
-        children.sort_by_key(|(_c, eval)| -eval + rng.gen_range(-10, 10));
+        let (best_move, best_child, score) = children[0];
+
+        // Store the root as well, so the next search of this state starts with its best move
+        let bound = if won { Bound::Lower } else { Bound::Exact };
+        searcher
+            .transpo_table
+            .put(state.hash_key(), score, depth, bound, Some(best_move));
+        let mut principal_variation = vec![best_move];
+        principal_variation.extend(walk_principal_variation(
+            best_child,
+            searcher.transpo_table,
+            depth,
+        ));
+
+        result = SearchResult {
+            best_move,
+            score,
+            proof: Proof::from_score(score, state.ply()),
+            depth,
+            nodes: searcher.nodes,
+            quiescence_nodes: searcher.quiescence_nodes,
+            cutoffs: searcher.cutoffs,
+            first_move_cutoffs: searcher.first_move_cutoffs,
+            elapsed: elapsed(),
+            principal_variation,
+            root_scores: children.iter().map(|(m, _, score)| (*m, *score)).collect(),
+        };
+        progress(&result);
+
+        if won || max_depth == Some(depth) {
+            return result;
+        }
 
         depth += 1;
     }
 }
 
+/// Follows the best moves stored in the transposition table from a state, for at most `length`
+/// moves
+///
+/// The line stops early when the table doesn't know the best move of a position, or when the game
+/// is over.
+pub fn walk_principal_variation<G: Game>(
+    mut state: G,
+    transpo_table: &TranspositionTable<G::Move>,
+    length: u32,
+) -> Vec<G::Move> {
+    let mut line = Vec::new();
+
+    while line.len() < length as usize && state.outcome().is_none() {
+        let best_move = match transpo_table.get(state.hash_key()) {
+            Some(entry) => entry.best_move,
+            None => None,
+        };
+
+        // Make sure the move is legal, in case two positions have the same key
+        match best_move.and_then(|m| state.legal_moves().find(|(action, _)| *action == m)) {
+            Some((action, child)) => {
+                line.push(action);
+                state = child;
+            }
+            None => break,
+        }
+    }
+
+    line
+}
+
 /// Returns some(value) if the calculation has time to finish, None otherwise
-pub fn negamax<F>(
-    board: Board,
+///
+/// Scores at or outside the window are bounds: a score `>= beta` is a lower bound and a score
+/// `<= alpha` is an upper bound of the value of the state.
+pub fn negamax<G, F>(
+    state: G,
     depth: u32,
-    mut alpha: i32,
-    mut beta: i32,
-    transpo_table: &mut TranspositionTable,
+    alpha: i32,
+    beta: i32,
+    transpo_table: &TranspositionTable<G::Move>,
     early_stop: &F,
 ) -> Option<i32>
 where
+    G: Evaluate,
     F: Fn() -> bool,
 {
-    if depth >= 3 && early_stop() {
-        return None;
-    }
+    Searcher::new(transpo_table, early_stop).negamax(state, depth, alpha, beta)
+}
 
-    if let Some(outcome) = board.outcome() {
-        let m = if board.turn() == Player::Player1 {
-            1
-        } else {
-            -1
-        };
+/// Returns the score of a finished game, from the perspective of the player to move
+fn outcome_score<G: Game>(state: G, outcome: Outcome) -> i32 {
+    let m = if state.turn() == Player::Player1 {
+        1
+    } else {
+        -1
+    };
 
-        let win_score = 1_000_000 - board.turn_number() as i32;
-        let value = match outcome {
-            Outcome::Player1Win => m * win_score,
-            Outcome::Player2Win => -m * win_score,
-            Outcome::Draw => 0,
-        };
-        return Some(value);
+    let win_score = WIN_SCORE - state.ply() as i32;
+    match outcome {
+        Outcome::Player1Win => m * win_score,
+        Outcome::Player2Win => -m * win_score,
+        Outcome::Draw => 0,
     }
+}
 
-    if depth == 0 {
-        return Some(eval(board));
-    }
+/// The state shared by the nodes of a search
+struct Searcher<'a, G: Game, F> {
+    transpo_table: &'a TranspositionTable<G::Move>,
+    early_stop: &'a F,
+    nodes: u64,
+    quiescence_nodes: u64,
+    cutoffs: u64,
+    first_move_cutoffs: u64,
+    /// The last moves that caused a cutoff at each ply, most recent first
+    killers: Vec<[Option<G::Move>; KILLERS]>,
+    /// How often each move caused a cutoff, weighted by depth and indexed by `Game::move_index`
+    history: Vec<u64>,
+    /// The moves of the state searched at each ply, with their priority
+    children: Vec<Vec<(G::Move, G, u64)>>,
+}
 
-    let mut max_score: i32 = 1_000_000 - board.turn_number() as i32 - 1;
-    if let Some(upper_bound) = transpo_table.get(board, depth) {
-        max_score = upper_bound;
+impl<'a, G, F> Searcher<'a, G, F>
+where
+    G: Evaluate,
+    F: Fn() -> bool,
+{
+    fn new(transpo_table: &'a TranspositionTable<G::Move>, early_stop: &'a F) -> Self {
+        Searcher {
+            transpo_table,
+            early_stop,
+            nodes: 0,
+            quiescence_nodes: 0,
+            cutoffs: 0,
+            first_move_cutoffs: 0,
+            killers: Vec::new(),
+            history: vec![0; G::MOVE_COUNT],
+            children: Vec::new(),
+        }
     }
 
-    if beta > max_score {
-        beta = max_score;
-        if alpha >= beta {
-            return Some(beta);
+    /// Searches the moves at the root with a principal variation search, storing their scores
+    ///
+    /// Returns the best score, or None if the time ran out. When a move fails high, it is moved to
+    /// the front and the remaining moves are not searched.
+    fn search_root(
+        &mut self,
+        children: &mut [(G::Move, G, i32)],
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> Option<i32> {
+        let mut best_score = i32::MIN;
+
+        for i in 0..children.len() {
+            let score = self.pvs_child(children[i].1, depth, alpha, beta, i == 0)?;
+            children[i].2 = score;
+
+            if score >= beta {
+                // Only the statistics count the cutoff, the root has no killers or history to keep
+                self.cutoffs += 1;
+                if i == 0 {
+                    self.first_move_cutoffs += 1;
+                }
+
+                children[..=i].rotate_right(1);
+                return Some(score);
+            }
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
         }
+
+        Some(best_score)
     }
 
-    let mut children = board.children(false);
+    /// Returns the score of a child from the parent's perspective
+    ///
+    /// Only the first child is searched with the full window. The others are searched with a null
+    /// window, which only proves that they are not better than alpha, and searched again with the
+    /// full window when they turn out to be.
+    fn pvs_child(
+        &mut self,
+        child: G,
+        depth: u32,
+        alpha: i32,
+        beta: i32,
+        first: bool,
+    ) -> Option<i32> {
+        if !first {
+            let score = -self.negamax(child, depth, -alpha - 1, -alpha)?;
+            if score <= alpha || score >= beta {
+                return Some(score);
+            }
+        }
 
-    // Order moves based on previous iterations
-    if depth > 3 {
-        children.sort_by_key(|c| transpo_table.get(*c, depth - 1).unwrap_or(0));
+        Some(-self.negamax(child, depth, -beta, -alpha)?)
     }
 
-    for c in children.into_iter() {
-        let search_result = negamax(c, depth - 1, -beta, -alpha, transpo_table, early_stop);
-        let score = if let Some(value) = search_result {
-            -value
-        } else {
+    fn negamax(&mut self, state: G, depth: u32, mut alpha: i32, mut beta: i32) -> Option<i32> {
+        self.nodes += 1;
+
+        if depth >= 3 && (self.early_stop)() {
             return None;
+        }
+
+        // The quiescence search checks whether the game is over
+        if depth == 0 {
+            let mut budget = QUIESCENCE_BUDGET;
+            return Some(self.quiescence(state, alpha, beta, &mut budget));
+        }
+
+        if let Some(outcome) = state.outcome() {
+            return Some(outcome_score(state, outcome));
+        }
+
+        let key = state.hash_key();
+        let mut tt_move = None;
+        if let Some(entry) = self.transpo_table.get(key) {
+            // Make sure the move is legal, in case two positions have the same key
+            tt_move = entry.best_move.filter(|&action| state.is_legal(action));
+
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return Some(entry.value),
+                    Bound::Lower => alpha = alpha.max(entry.value),
+                    Bound::Upper => beta = beta.min(entry.value),
+                }
+
+                if alpha >= beta {
+                    return Some(entry.value);
+                }
+            }
+        }
+
+        // The player to move can't win before its next move
+        let max_score: i32 = WIN_SCORE - state.ply() as i32 - 1;
+        if beta > max_score {
+            beta = max_score;
+            if alpha >= beta {
+                return Some(beta);
+            }
+        }
+
+        let ply = state.ply() as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; KILLERS]);
+            self.children.resize_with(ply + 1, Vec::new);
+        }
+
+        let alpha_orig = alpha;
+        let mut best_score = i32::MIN;
+        let mut best_move = None;
+
+        // The move of the table is searched before generating the others, since it often causes
+        // a cutoff on its own
+        if let Some(action) = tt_move {
+            let score = self.pvs_child(state.apply(action), depth - 1, alpha, beta, true)?;
+            best_score = score;
+            best_move = Some(action);
+            alpha = alpha.max(score);
+
+            if alpha >= beta {
+                self.cutoff(action, ply, depth, tt_move, true);
+            }
+        }
+
+        if alpha < beta {
+            // The list of each ply is reused, so the nodes don't allocate
+            let mut children = std::mem::take(&mut self.children[ply]);
+            children.clear();
+            children.extend(
+                state
+                    .legal_moves()
+                    .filter(|(action, _)| Some(*action) != tt_move)
+                    .map(|(action, child)| (action, child, self.move_order(action, ply))),
+            );
+
+            for i in 0..children.len() {
+                // Only the moves that get searched are ordered, since most nodes end with a cutoff
+                let next = (i..children.len())
+                    .max_by_key(|&j| children[j].2)
+                    .expect("the range isn't empty");
+                children.swap(i, next);
+
+                let (action, child, _) = children[i];
+                let first = i == 0 && tt_move.is_none();
+                let score = self.pvs_child(child, depth - 1, alpha, beta, first)?;
+
+                if score > best_score {
+                    best_score = score;
+                    best_move = Some(action);
+                }
+
+                if score > alpha {
+                    alpha = score
+                }
+
+                if alpha >= beta {
+                    self.cutoff(action, ply, depth, tt_move, first);
+                    break;
+                }
+            }
+
+            self.children[ply] = children;
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > alpha_orig {
+            Bound::Exact
+        } else {
+            // All the moves failed low, so the previous best move is more trustworthy
+            best_move = tt_move.or(best_move);
+            Bound::Upper
+        };
+        self.transpo_table
+            .put(key, best_score, depth, bound, best_move);
+
+        Some(best_score)
+    }
+
+    /// Searches only the forcing moves, until the states are quiet or the budget is spent, and
+    /// returns the evaluation of the quiet states
+    fn quiescence(&mut self, state: G, mut alpha: i32, beta: i32, budget: &mut u32) -> i32 {
+        self.quiescence_nodes += 1;
+
+        if let Some(outcome) = state.outcome() {
+            return outcome_score(state, outcome);
+        }
+
+        let moves = match state.forcing_moves() {
+            Some(moves) if *budget > 0 => moves,
+            _ => return state.eval(),
         };
 
-        if score >= beta {
-            return Some(score);
+        let mut best_score = i32::MIN;
+        for (_, child) in moves {
+            *budget = budget.saturating_sub(1);
+            let score = -self.quiescence(child, -beta, -alpha, budget);
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
         }
 
-        if score > alpha {
-            alpha = score
+        best_score
+    }
+
+    /// Returns the priority of a move: the killer moves first, then the other moves by history
+    ///
+    /// The move of the transposition table isn't ordered, since it is searched before the others.
+    fn move_order(&self, action: G::Move, ply: usize) -> u64 {
+        match self.killers[ply].iter().position(|k| *k == Some(action)) {
+            Some(i) => u64::MAX - i as u64,
+            None => self.history[G::move_index(action)],
         }
     }
 
-    transpo_table.put(board, alpha, depth);
-    Some(alpha)
+    /// Remembers the move that caused a cutoff, to try it early in other states
+    fn cutoff(
+        &mut self,
+        action: G::Move,
+        ply: usize,
+        depth: u32,
+        tt_move: Option<G::Move>,
+        first: bool,
+    ) {
+        self.cutoffs += 1;
+        if first {
+            self.first_move_cutoffs += 1;
+        }
+
+        if Some(action) == tt_move {
+            return;
+        }
+
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(action) {
+            killers.rotate_right(1);
+            killers[0] = Some(action);
+        }
+
+        // Deep cutoffs save more work, so they count more
+        self.history[G::move_index(action)] += u64::from(depth * depth);
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::game::Action;
+    use crate::game::Board;
+    use crate::game::Position;
+    use crate::game::Swap;
 
     #[test]
     fn negamax_test1() {
-        let board = Board::new([
+        let board = Position::from(Board::new([
             [1, 2, 0, 0, 0, 0],
             [1, 2, 0, 1, 2, 0],
             [1, 2, 0, 0, 0, 0],
             [0, 0, 0, 0, 0, 0],
             [0, 0, 0, 0, 0, 0],
             [0, 0, 0, 0, 0, 0],
-        ]);
+        ]));
 
         assert_eq!(
             negamax(
@@ -158,7 +733,7 @@ mod test {
                 3,
                 -1_000_000,
                 1_000_000,
-                &mut TranspositionTable::new(1),
+                &TranspositionTable::new(1),
                 &|| false
             )
             .unwrap(),
@@ -168,14 +743,14 @@ mod test {
 
     #[test]
     fn negamax_test2() {
-        let board = Board::new([
+        let board = Position::from(Board::new([
             [1, 0, 0, 1, 2, 1],
             [0, 1, 0, 0, 2, 0],
             [0, 0, 0, 0, 2, 0],
             [0, 0, 0, 1, 1, 0],
             [0, 2, 0, 0, 2, 0],
             [0, 0, 0, 0, 0, 0],
-        ]);
+        ]));
 
         assert_eq!(
             negamax(
@@ -183,11 +758,281 @@ mod test {
                 3,
                 -1_000_000,
                 1_000_000,
-                &mut TranspositionTable::new(1),
+                &TranspositionTable::new(1),
                 &|| false
             )
             .unwrap(),
             999_988
         );
     }
+
+    #[test]
+    fn negamax_last_square_win_test() {
+        // Filling the last square also completes player 2's row, which is a win, not a draw
+        let board = Position::from(Board::new([
+            [0, 2, 2, 2, 2, 1],
+            [1, 2, 1, 1, 1, 2],
+            [2, 2, 1, 2, 1, 1],
+            [1, 1, 2, 1, 2, 2],
+            [2, 1, 1, 2, 1, 1],
+            [1, 2, 2, 1, 2, 1],
+        ]));
+
+        assert_eq!(
+            negamax(
+                board,
+                1,
+                -1_000_000,
+                1_000_000,
+                &TranspositionTable::new(1),
+                &|| false
+            )
+            .unwrap(),
+            999_964
+        );
+    }
+
+    #[test]
+    fn negamax_position_test() {
+        let board = Board::new([
+            [1, 0, 0, 1, 2, 1],
+            [0, 1, 0, 0, 2, 0],
+            [0, 0, 0, 0, 2, 0],
+            [0, 0, 0, 1, 1, 0],
+            [0, 2, 0, 0, 2, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+
+        // A position reached by playing moves, with its hash updated along the way, is searched
+        // like the same position built from its board
+        let (_, child) = Position::from(board).legal_moves().nth(10).unwrap();
+        let (_, played) = child.legal_moves().nth(20).unwrap();
+        let built = Position::from(played.board());
+        assert_eq!(played.hash_key(), built.hash_key());
+
+        for depth in 1..4 {
+            assert_eq!(
+                negamax(
+                    played,
+                    depth,
+                    -1_000_000,
+                    1_000_000,
+                    &TranspositionTable::new(1),
+                    &|| false
+                ),
+                negamax(
+                    built,
+                    depth,
+                    -1_000_000,
+                    1_000_000,
+                    &TranspositionTable::new(1),
+                    &|| false
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn negamax_transpo_table_test() {
+        let board = Position::from(Board::new([
+            [1, 0, 0, 1, 2, 1],
+            [0, 1, 0, 0, 2, 0],
+            [0, 0, 0, 0, 2, 0],
+            [0, 0, 0, 1, 1, 0],
+            [0, 2, 0, 0, 2, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]));
+        let transpo_table = TranspositionTable::new(1_000_000);
+
+        for depth in 1..4 {
+            negamax(board, depth, -1_000_000, 1_000_000, &transpo_table, &|| {
+                false
+            });
+        }
+        let entry = transpo_table.get(board.hash_key()).unwrap();
+        assert_eq!(entry.value, 999_988);
+        assert_eq!(entry.depth, 3);
+
+        // The stored move leads to the stored score
+        let child = board.apply(entry.best_move.unwrap());
+        let fresh_table = TranspositionTable::new(1);
+        assert_eq!(
+            negamax(child, 2, -1_000_000, 1_000_000, &fresh_table, &|| false),
+            Some(-999_988)
+        );
+    }
+
+    #[test]
+    fn negamax_illegal_transpo_move_test() {
+        let board = Position::from(Board::new([
+            [1, 0, 0, 1, 2, 1],
+            [0, 1, 0, 0, 2, 0],
+            [0, 0, 0, 0, 2, 0],
+            [0, 0, 0, 1, 1, 0],
+            [0, 2, 0, 0, 2, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]));
+
+        // An entry of another position, whose move would put an o on the x that blocks its column
+        let transpo_table = TranspositionTable::new(1_000_000);
+        let illegal = Action::new(22, Swap::TL_BL);
+        transpo_table.put(board.hash_key(), 0, 0, Bound::Upper, Some(illegal));
+
+        let fresh_table = TranspositionTable::new(1_000_000);
+        assert_eq!(
+            negamax(board, 2, -1_000_000, 1_000_000, &transpo_table, &|| false),
+            negamax(board, 2, -1_000_000, 1_000_000, &fresh_table, &|| false)
+        );
+        let entry = transpo_table.get(board.hash_key()).unwrap();
+        assert!(board.is_legal(entry.best_move.unwrap()));
+    }
+
+    #[test]
+    fn principal_variation_test() {
+        let board = Position::from(Board::new([
+            [1, 0, 0, 1, 2, 1],
+            [0, 1, 0, 0, 2, 0],
+            [0, 0, 0, 0, 2, 0],
+            [0, 0, 0, 1, 1, 0],
+            [0, 2, 0, 0, 2, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]));
+        let transpo_table = TranspositionTable::new(1_000_000);
+        let score = negamax(board, 3, -1_000_000, 1_000_000, &transpo_table, &|| false);
+        assert_eq!(score, Some(999_988));
+
+        // The line ends with the win the score announces
+        let line = walk_principal_variation(board, &transpo_table, 3);
+        let end = line
+            .iter()
+            .fold(board, |state, action| state.apply(*action));
+        assert_eq!(end.outcome(), Some(Outcome::win(board.turn())));
+        assert_eq!(end.ply(), 12);
+    }
+
+    #[test]
+    fn search_test() {
+        let board = Position::from(Board::new([
+            [1, 2, 0, 0, 0, 0],
+            [1, 2, 0, 1, 2, 0],
+            [1, 2, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]));
+        let mut iterations = Vec::new();
+        let result = search_with_progress(
+            board,
+            Duration::from_secs(60),
+            &TranspositionTable::new(1_000_000),
+            |result| iterations.push(result.depth),
+        );
+
+        assert_eq!(iterations, vec![3]);
+        assert_eq!(result.score, 999_991);
+        assert_eq!(result.proof, Some(Proof::Win(1)));
+        assert_eq!(result.principal_variation, vec![result.best_move]);
+        assert_eq!(result.root_scores[0], (result.best_move, 999_991));
+        assert_eq!(result.root_scores.len(), board.legal_moves().count());
+        assert!(result.nodes > 0);
+        assert!(result.cutoffs > 0);
+        assert!(result.first_move_cutoff_rate() > 0.5);
+        assert_eq!(
+            board.apply(result.best_move).outcome(),
+            Some(Outcome::Player1Win)
+        );
+
+        assert_eq!(Proof::from_score(-999_980, 8), Some(Proof::Loss(12)));
+        assert_eq!(Proof::from_score(300, 8), None);
+    }
+
+    #[test]
+    fn search_to_depth_test() {
+        let board = Position::from(
+            "x.o...o..x....x..............o..x..o x"
+                .parse::<Board>()
+                .unwrap(),
+        );
+
+        // The null windows and the aspiration windows don't change the score
+        for depth in 1..3 {
+            let result = search_to_depth(board, depth, &TranspositionTable::new(1_000_000));
+            let score = negamax(
+                board,
+                depth + 1,
+                -WIN_SCORE,
+                WIN_SCORE,
+                &TranspositionTable::new(1_000_000),
+                &|| false,
+            );
+
+            assert_eq!(result.depth, depth);
+            assert_eq!(Some(result.score), score);
+            assert!(result.cutoffs > 0);
+            assert!(result.first_move_cutoff_rate() > 0.5);
+            assert_eq!(
+                result,
+                SearchResult {
+                    elapsed: result.elapsed,
+                    ..search_to_depth(board, depth, &TranspositionTable::new(1_000_000))
+                }
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "can't search a finished game")]
+    fn search_finished_test() {
+        let board: Board = "xxxxx.oooo.......................... o".parse().unwrap();
+        search_to_depth(Position::from(board), 2, &TranspositionTable::new(1_000));
+    }
+
+    #[test]
+    fn move_order_test() {
+        let transpo_table = TranspositionTable::new(1);
+        let mut searcher: Searcher<Position, _> = Searcher::new(&transpo_table, &|| false);
+        searcher.killers.resize(10, [None; KILLERS]);
+        let [a, b, c, d] =
+            ["a1/TL-TR", "b1/TL-TR", "c1/TL-TR", "d1/TL-TR"].map(|a| a.parse().unwrap());
+
+        searcher.cutoff(a, 8, 2, None, true);
+        searcher.cutoff(b, 8, 3, None, false);
+        searcher.cutoff(c, 8, 1, Some(c), true);
+        searcher.cutoff(d, 9, 1, None, false);
+
+        // The killers come first, most recent first, then history. The move of the table is
+        // neither a killer nor in the history.
+        let order = |action| searcher.move_order(action, 8);
+        assert_eq!(order(c), 0);
+        assert!(order(b) > order(a));
+        assert!(order(a) > order(d));
+        assert_eq!(order(d), 1);
+        assert_eq!(searcher.history[a.index()], 4);
+        assert_eq!((searcher.cutoffs, searcher.first_move_cutoffs), (4, 2));
+    }
+
+    #[test]
+    fn search_parallel_test() {
+        let board = Position::from(Board::new([
+            [1, 0, 0, 1, 2, 1],
+            [0, 1, 0, 0, 2, 0],
+            [0, 0, 0, 0, 2, 0],
+            [0, 0, 0, 1, 1, 0],
+            [0, 2, 0, 0, 2, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]));
+        let transpo_table = TranspositionTable::new(1_000_000);
+        let result = search_parallel(board, Duration::from_secs(60), 4, &transpo_table, |_| {});
+
+        // Any forced win ends the search, and the helpers may find a slower one first
+        assert!(matches!(result.proof, Some(Proof::Win(_))));
+        assert!(board
+            .legal_moves()
+            .any(|(action, _)| action == result.best_move));
+        assert!(transpo_table.stats().stores > 0);
+
+        // The threads try the immediate wins first, whatever order they start with
+        assert_eq!(result.score, 999_988);
+        assert_eq!(result.proof, Some(Proof::Win(1)));
+    }
 }
@@ -1,49 +1,335 @@
-use crate::game::Board;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hash;
-use std::hash::Hasher;
+use crate::game::Action;
+use std::mem::size_of;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
 
-pub struct TranspositionTable {
-    vec: Vec<Entry>,
+/// Number of entries sharing an index
+const BUCKET_SIZE: usize = 4;
+
+/// Number of parts of the table that can be used by different threads at the same time
+const SHARDS: usize = 64;
+
+/// How many plies of depth an entry is worth per search it is older than the current one, when
+/// choosing which entry to replace
+const AGE_WEIGHT: i32 = 4;
+
+/// Which side of the true value a stored score lies on
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bound {
+    /// The score is the value of the position
+    Exact,
+    /// The search failed high, the value is at least the score
+    Lower,
+    /// The search failed low, the value is at most the score
+    Upper,
+}
+
+/// What a search found about a position
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Entry<M = Action> {
+    pub key: u64,
+    pub value: i32,
+    pub depth: u32,
+    pub bound: Bound,
+    /// The move that was best or caused the cutoff, if any
+    pub best_move: Option<M>,
+    /// The search that stored the entry, see [`TranspositionTable::new_search`]
+    pub generation: u8,
 }
 
-impl TranspositionTable {
-    pub fn new(size: usize) -> TranspositionTable {
-        let mut vec = Vec::with_capacity(size);
+type Bucket<M> = [Option<Entry<M>>; BUCKET_SIZE];
+
+/// Counters of how the table has been used since it was created or cleared
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub struct TableStats {
+    /// Number of lookups
+    pub probes: u64,
+    /// Number of lookups that found the position
+    pub hits: u64,
+    /// Number of entries stored
+    pub stores: u64,
+    /// Number of entries of other positions that were replaced to store an entry
+    pub collisions: u64,
+    /// Number of slots holding an entry
+    pub filled: usize,
+    /// Number of slots
+    pub capacity: usize,
+}
 
-        for _ in 0..size {
-            vec.push(Entry::default());
+impl TableStats {
+    pub fn hit_rate(self) -> f64 {
+        if self.probes == 0 {
+            return 0.;
         }
 
-        TranspositionTable { vec }
+        self.hits as f64 / self.probes as f64
     }
 
-    pub fn put(&mut self, key: Board, value: i32, depth: u32) {
-        let i = self.index(key);
-        self.vec[i] = Entry { key, value, depth };
+    pub fn fill_rate(self) -> f64 {
+        self.filled as f64 / self.capacity as f64
     }
+}
 
-    pub fn get(&self, key: Board, min_depth: u32) -> Option<i32> {
-        let i = self.index(key);
-        let entry = &self.vec[i];
+/// Cache of search results, indexed by the hash of the positions
+///
+/// Entries are grouped in buckets. When a bucket is full, the entry that is the least useful is
+/// replaced: shallow entries and entries from older searches go first.
+///
+/// The buckets are split in shards, each behind its own lock, so threads searching at the same
+/// time can share the table and rarely wait for each other.
+pub struct TranspositionTable<M = Action> {
+    shards: Vec<Mutex<Vec<Bucket<M>>>>,
+    buckets_per_shard: usize,
+    generation: AtomicU8,
+    probes: AtomicU64,
+    hits: AtomicU64,
+    stores: AtomicU64,
+    collisions: AtomicU64,
+    filled: AtomicUsize,
+}
+
+impl<M: Copy> TranspositionTable<M> {
+    /// Creates a table holding at least the given number of entries
+    pub fn new(size: usize) -> TranspositionTable<M> {
+        let buckets = size.div_ceil(BUCKET_SIZE).max(1);
+        let shards = buckets.min(SHARDS);
+        let buckets_per_shard = buckets.div_ceil(shards);
 
-        if entry.key == key && entry.depth >= min_depth {
-            Some(entry.value)
+        TranspositionTable {
+            shards: (0..shards)
+                .map(|_| Mutex::new(vec![[None; BUCKET_SIZE]; buckets_per_shard]))
+                .collect(),
+            buckets_per_shard,
+            generation: AtomicU8::new(0),
+            probes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            stores: AtomicU64::new(0),
+            collisions: AtomicU64::new(0),
+            filled: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a table using about the given amount of memory
+    pub fn with_megabytes(megabytes: usize) -> TranspositionTable<M> {
+        let buckets = megabytes * 1024 * 1024 / size_of::<Bucket<M>>();
+
+        // Round down to fill the shards evenly, so the table doesn't grow past the budget
+        let buckets = if buckets >= SHARDS {
+            buckets / SHARDS * SHARDS
         } else {
-            None
+            buckets
+        };
+        TranspositionTable::new(buckets * BUCKET_SIZE)
+    }
+
+    /// Returns the number of entries the table can hold
+    pub fn capacity(&self) -> usize {
+        self.shards.len() * self.buckets_per_shard * BUCKET_SIZE
+    }
+
+    /// Marks the start of a new search. Entries of previous searches are kept, but are replaced
+    /// first.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Clears the table before a new game, since its positions are unlikely to come back
+    pub fn new_game(&self) {
+        for shard in self.shards.iter() {
+            for bucket in lock(shard).iter_mut() {
+                *bucket = [None; BUCKET_SIZE];
+            }
         }
+
+        self.generation.store(0, Ordering::Relaxed);
+        self.probes.store(0, Ordering::Relaxed);
+        self.hits.store(0, Ordering::Relaxed);
+        self.stores.store(0, Ordering::Relaxed);
+        self.collisions.store(0, Ordering::Relaxed);
+        self.filled.store(0, Ordering::Relaxed);
     }
 
-    fn index(&self, key: Board) -> usize {
-        let mut s = DefaultHasher::new();
-        key.hash(&mut s);
-        s.finish() as usize % self.vec.len()
+    pub fn put(&self, key: u64, value: i32, depth: u32, bound: Bound, best_move: Option<M>) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let (shard, i) = self.index(key);
+        let mut shard = lock(&self.shards[shard]);
+        let bucket = &mut shard[i];
+
+        let same_key = bucket.iter().position(|e| e.is_some_and(|e| e.key == key));
+
+        let slot = match same_key {
+            Some(slot) => slot,
+            None => match bucket.iter().position(Option::is_none) {
+                Some(slot) => {
+                    self.filled.fetch_add(1, Ordering::Relaxed);
+                    slot
+                }
+                None => {
+                    self.collisions.fetch_add(1, Ordering::Relaxed);
+                    (0..BUCKET_SIZE)
+                        .min_by_key(|&slot| {
+                            let entry = bucket[slot].expect("the bucket is full");
+                            let age = generation.wrapping_sub(entry.generation);
+                            entry.depth as i32 - AGE_WEIGHT * i32::from(age)
+                        })
+                        .expect("buckets aren't empty")
+                }
+            },
+        };
+
+        // Keep the move of a previous search of the position when this one didn't find one
+        let best_move = match same_key {
+            Some(slot) => best_move.or_else(|| bucket[slot].and_then(|e| e.best_move)),
+            None => best_move,
+        };
+
+        bucket[slot] = Some(Entry {
+            key,
+            value,
+            depth,
+            bound,
+            best_move,
+            generation,
+        });
+        self.stores.fetch_add(1, Ordering::Relaxed);
     }
+
+    /// Returns the entry stored for the key, whatever its depth
+    pub fn get(&self, key: u64) -> Option<Entry<M>> {
+        self.probes.fetch_add(1, Ordering::Relaxed);
+
+        let (shard, i) = self.index(key);
+        let entry = lock(&self.shards[shard])[i]
+            .iter()
+            .filter_map(|entry| *entry)
+            .find(|entry| entry.key == key);
+
+        if entry.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+
+        entry
+    }
+
+    pub fn stats(&self) -> TableStats {
+        TableStats {
+            probes: self.probes.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            stores: self.stores.load(Ordering::Relaxed),
+            collisions: self.collisions.load(Ordering::Relaxed),
+            filled: self.filled.load(Ordering::Relaxed),
+            capacity: self.capacity(),
+        }
+    }
+
+    /// Returns the shard and the index in the shard of the bucket of the key
+    fn index(&self, key: u64) -> (usize, usize) {
+        let bucket = (key % (self.shards.len() * self.buckets_per_shard) as u64) as usize;
+        (bucket % self.shards.len(), bucket / self.shards.len())
+    }
+}
+
+/// Locks a shard. The entries are written in one step, so a panic while the lock was held can't
+/// leave them half written.
+fn lock<T>(shard: &Mutex<T>) -> MutexGuard<'_, T> {
+    shard
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
 }
 
-#[derive(Debug, Default)]
-struct Entry {
-    key: Board,
-    value: i32,
-    depth: u32,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stores an entry whose value and move are its key
+    fn put(table: &TranspositionTable<u8>, key: u64, depth: u32) {
+        table.put(key, key as i32, depth, Bound::Exact, Some(key as u8));
+    }
+
+    #[test]
+    fn replacement_test() {
+        let table = TranspositionTable::new(BUCKET_SIZE);
+        for key in 0..4 {
+            put(&table, key, 5 + key as u32);
+        }
+
+        // The shallowest entry is replaced
+        put(&table, 4, 1);
+        assert_eq!(table.get(0), None);
+        assert_eq!(table.get(4).map(|e| e.value), Some(4));
+
+        // Entries of older searches are replaced before deeper ones
+        table.new_search();
+        table.new_search();
+        put(&table, 5, 1);
+        put(&table, 6, 1);
+        assert_eq!(table.get(1), None);
+        assert!(table.get(5).is_some());
+        assert!(table.get(3).is_some());
+
+        let stats = table.stats();
+        assert_eq!(stats.collisions, 3);
+        assert_eq!(stats.filled, 4);
+        assert_eq!(stats.fill_rate(), 1.);
+    }
+
+    #[test]
+    fn same_key_test() {
+        let table = TranspositionTable::new(BUCKET_SIZE);
+        put(&table, 1, 3);
+        table.put(1, 10, 4, Bound::Lower, None);
+
+        let entry = table.get(1).unwrap();
+        assert_eq!(
+            (entry.value, entry.depth, entry.bound),
+            (10, 4, Bound::Lower)
+        );
+        assert_eq!(entry.best_move, Some(1));
+        assert_eq!(table.stats().filled, 1);
+    }
+
+    #[test]
+    fn new_game_test() {
+        let table = TranspositionTable::new(100);
+        put(&table, 1, 3);
+        assert!(table.get(1).is_some());
+        assert!(table.get(2).is_none());
+        assert_eq!(table.stats().hit_rate(), 0.5);
+
+        table.new_game();
+        assert!(table.get(1).is_none());
+        assert_eq!(table.stats().filled, 0);
+    }
+
+    #[test]
+    fn shared_test() {
+        let table = TranspositionTable::new(100_000);
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let table = &table;
+                scope.spawn(move || {
+                    for key in (thread..10_000).step_by(4) {
+                        table.put(key, key as i32, 1, Bound::Exact, Some(key as u8));
+                    }
+                });
+            }
+        });
+
+        assert!((0..10_000).all(|key| table.get(key).map(|e| e.value) == Some(key as i32)));
+        assert_eq!(table.stats().stores, 10_000);
+    }
+
+    #[test]
+    fn with_megabytes_test() {
+        let table: TranspositionTable = TranspositionTable::with_megabytes(1);
+        let size = table.capacity() * size_of::<Option<Entry>>();
+
+        assert!(size <= 1024 * 1024);
+        assert!(size > 1000 * 1024);
+    }
 }
@@ -0,0 +1,264 @@
+//! Texel-style tuning of the evaluation weights
+//!
+//! Positions from finished games are labeled with the result of their game, and the weights are
+//! fitted so that a logistic function of the evaluation predicts these results, by minimizing the
+//! log loss with a local search.
+
+use crate::alpha_beta::search_to_depth;
+use crate::alpha_beta::Evaluate;
+use crate::alpha_beta::TranspositionTable;
+use crate::alpha_beta::Weights;
+use crate::engine::Game;
+use crate::game::GameRecord;
+use crate::game::Outcome;
+use crate::game::Player;
+use crate::game::Position;
+use rand::Rng;
+
+/// Largest change of a weight tried by the local search
+const MAX_STEP: i32 = 16;
+
+/// A position labeled with the result of the game it was played in
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Sample {
+    pub position: Position,
+    /// Score of the player to move: 1 for a win, 0.5 for a draw and 0 for a loss
+    pub result: f64,
+}
+
+/// Returns the labeled positions of a finished game, or nothing if the game isn't over
+///
+/// Only quiet positions are kept, since the evaluation isn't meant to predict the result of a
+/// position with threats on the board.
+pub fn samples(game: &GameRecord) -> Vec<Sample> {
+    let outcome = match game.outcome() {
+        Some(outcome) => outcome,
+        None => return Vec::new(),
+    };
+
+    (0..game.ply())
+        .filter_map(|ply| game.position_at(ply))
+        .map(Position::from)
+        .filter(|&position| position.forcing_moves().is_none())
+        .map(|position| Sample {
+            position,
+            result: result(position.turn(), outcome),
+        })
+        .collect()
+}
+
+/// Returns the score of the player in a game with the given outcome
+fn result(player: Player, outcome: Outcome) -> f64 {
+    match (player, outcome) {
+        (_, Outcome::Draw) => 0.5,
+        (Player::Player1, Outcome::Player1Win) | (Player::Player2, Outcome::Player2Win) => 1.,
+        _ => 0.,
+    }
+}
+
+/// Plays a game of the engine against itself, searching every move to the given depth
+///
+/// The first moves are random, so that the games don't all repeat the same opening.
+pub fn self_play<R: Rng>(rng: &mut R, random_moves: usize, depth: u32) -> GameRecord {
+    let transpo_table = TranspositionTable::new(1 << 16);
+    let mut game = GameRecord::new();
+
+    while game.outcome().is_none() {
+        let position = Position::from(game.board());
+        let action = if game.ply() < random_moves {
+            position.random_move(rng).expect("the game isn't over").0
+        } else {
+            search_to_depth(position, depth, &transpo_table).best_move
+        };
+
+        game.play(action)
+            .expect("the engine played an illegal move");
+    }
+
+    game
+}
+
+/// A sample reduced to what the loss depends on
+///
+/// The evaluation is linear in the weights, so it is the dot product of the weights with the
+/// evaluation of each weight alone, which only has to be computed once.
+struct Features {
+    values: [i32; Weights::PARAMETERS],
+    result: f64,
+}
+
+impl Features {
+    fn new(sample: &Sample) -> Features {
+        let mut values = [0; Weights::PARAMETERS];
+        for (i, value) in values.iter_mut().enumerate() {
+            let mut parameters = [0; Weights::PARAMETERS];
+            parameters[i] = 1;
+            *value = Weights::from_parameters(parameters).eval(sample.position);
+        }
+
+        Features {
+            values,
+            result: sample.result,
+        }
+    }
+
+    fn eval(&self, parameters: &[i32; Weights::PARAMETERS]) -> i32 {
+        self.values
+            .iter()
+            .zip(parameters)
+            .map(|(value, weight)| value * weight)
+            .sum()
+    }
+}
+
+/// Returns the mean log loss of predicting the results of the samples from their evaluations
+///
+/// An evaluation `e` predicts a score of `1 / (1 + exp(-scale * e))` for the player to move.
+pub fn log_loss(weights: &Weights, samples: &[Sample], scale: f64) -> f64 {
+    let features: Vec<Features> = samples.iter().map(Features::new).collect();
+    mean_log_loss(&features, &weights.parameters(), scale)
+}
+
+fn mean_log_loss(
+    features: &[Features],
+    parameters: &[i32; Weights::PARAMETERS],
+    scale: f64,
+) -> f64 {
+    if features.is_empty() {
+        return 0.;
+    }
+
+    let total: f64 = features
+        .iter()
+        .map(|features| {
+            let prediction = 1. / (1. + (-scale * f64::from(features.eval(parameters))).exp());
+            let prediction = prediction.clamp(1e-9, 1. - 1e-9);
+            -(features.result * prediction.ln() + (1. - features.result) * (1. - prediction).ln())
+        })
+        .sum();
+    total / features.len() as f64
+}
+
+/// Returns the scale of the logistic function that best fits the weights to the samples
+///
+/// The weights are tuned with this scale fixed, so they keep the units of the current evaluation.
+pub fn fit_scale(weights: &Weights, samples: &[Sample]) -> f64 {
+    let features: Vec<Features> = samples.iter().map(Features::new).collect();
+    let parameters = weights.parameters();
+    let loss = |log_scale: f64| mean_log_loss(&features, &parameters, log_scale.exp());
+
+    // The log loss is convex in the scale, so a ternary search finds the best one
+    let (mut low, mut high) = (1e-6f64.ln(), 1f64.ln());
+    for _ in 0..100 {
+        let a = low + (high - low) / 3.;
+        let b = high - (high - low) / 3.;
+        if loss(a) < loss(b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+
+    ((low + high) / 2.).exp()
+}
+
+/// Fits the weights to the samples by minimizing the log loss with a local search
+///
+/// Every weight is moved up and down by a step, keeping the changes that lower the loss, and the
+/// step is halved once no change helps. The progress callback gets the weights and their loss
+/// after every pass over the weights.
+pub fn tune<F: FnMut(&Weights, f64)>(
+    weights: Weights,
+    samples: &[Sample],
+    scale: f64,
+    mut progress: F,
+) -> Weights {
+    let features: Vec<Features> = samples.iter().map(Features::new).collect();
+    let mut parameters = weights.parameters();
+    let mut best_loss = mean_log_loss(&features, &parameters, scale);
+    let mut step = MAX_STEP;
+
+    while step > 0 {
+        let mut improved = false;
+
+        for i in 0..Weights::PARAMETERS {
+            for &delta in [step, -step].iter() {
+                parameters[i] += delta;
+                let loss = mean_log_loss(&features, &parameters, scale);
+
+                if loss < best_loss {
+                    best_loss = loss;
+                    improved = true;
+                    break;
+                }
+
+                parameters[i] -= delta;
+            }
+        }
+
+        progress(&Weights::from_parameters(parameters), best_loss);
+
+        if !improved {
+            step /= 2;
+        }
+    }
+
+    Weights::from_parameters(parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Board;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn samples_test() {
+        let game: GameRecord = "1. a1/TL-TR f6/TL-TR\n2. b1/TL-TR f5/TL-TR\n*\n"
+            .parse()
+            .unwrap();
+        assert!(samples(&game).is_empty());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let game = self_play(&mut rng, 4, 1);
+        let outcome = game.outcome().unwrap();
+        let samples = samples(&game);
+
+        assert!(!samples.is_empty());
+        assert_eq!(samples[0].position, Position::from(Board::default()));
+        for sample in samples {
+            assert!(sample.position.outcome().is_none());
+            assert_eq!(sample.result, result(sample.position.turn(), outcome));
+        }
+    }
+
+    #[test]
+    fn tune_test() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let samples: Vec<Sample> = (0..4)
+            .flat_map(|_| samples(&self_play(&mut rng, 4, 1)))
+            .collect();
+
+        let scale = fit_scale(&Weights::DEFAULT, &samples);
+        assert!(scale > 0.);
+
+        // The features reproduce the evaluation
+        let features = Features::new(&samples[10]);
+        assert_eq!(
+            features.eval(&Weights::DEFAULT.parameters()),
+            Weights::DEFAULT.eval(samples[10].position)
+        );
+
+        let before = log_loss(&Weights::DEFAULT, &samples, scale);
+        let mut losses = Vec::new();
+        let weights = tune(Weights::DEFAULT, &samples, scale, |_, loss| {
+            losses.push(loss)
+        });
+        let after = log_loss(&weights, &samples, scale);
+
+        assert!(after < before);
+        assert_eq!(losses.last(), Some(&after));
+        assert!(losses.windows(2).all(|pair| pair[1] <= pair[0]));
+    }
+}
@@ -0,0 +1,70 @@
+//! The interface between the games and the search engines
+
+use crate::game::Outcome;
+use crate::game::Player;
+use crate::game::Rules;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use std::fmt::Debug;
+
+/// A two-player game that the search engines can play
+///
+/// Implementing this trait is enough to run `alpha_beta` and `mcts` on a game. The game state is
+/// expected to be a small value type, cheap to copy.
+pub trait Game: Copy + Eq + Debug {
+    /// An action that can be played in the game
+    type Move: Copy + Eq + Debug;
+
+    /// Iterator over the legal moves of a state, along with the states they lead to
+    type Moves: Iterator<Item = (Self::Move, Self)>;
+
+    /// The rules that decide when the game is over, which the engines score finished games with
+    type Rules: Rules<Self> + Default;
+
+    /// Number of different moves in the game, see [`Game::move_index`]
+    const MOVE_COUNT: usize;
+
+    /// Returns a number below `MOVE_COUNT` identifying the move, to index tables of moves
+    fn move_index(action: Self::Move) -> usize;
+
+    /// Returns the legal moves from this state, along with the states they lead to
+    fn legal_moves(self) -> Self::Moves;
+
+    /// Returns the state reached by playing the given move
+    fn apply(self, action: Self::Move) -> Self;
+
+    /// Returns whether the move can be played from this state, which moves read from a
+    /// transposition table are checked with, in case two states have the same key
+    ///
+    /// Looks for the move among the legal moves by default.
+    fn is_legal(self, action: Self::Move) -> bool {
+        self.legal_moves().any(|(legal, _)| legal == action)
+    }
+
+    /// Returns the outcome of the game under its rules, or None if the game is still going
+    fn outcome(self) -> Option<Outcome> {
+        Self::Rules::default().outcome(self)
+    }
+
+    /// Returns the player whose turn it is
+    fn turn(self) -> Player;
+
+    /// Returns the number of moves played to reach this state
+    fn ply(self) -> u32;
+
+    /// Returns a 64 bit hash of this state, used to key the transposition tables
+    fn hash_key(self) -> u64;
+
+    /// Returns the legal moves, but only the moves that prevent the opponent from winning on its
+    /// next turn when it threatens to
+    ///
+    /// Returns all the legal moves by default.
+    fn filtered_moves(self) -> Vec<(Self::Move, Self)> {
+        self.legal_moves().collect()
+    }
+
+    /// Returns a random legal move, used to run simulations
+    fn random_move<R: Rng>(self, rng: &mut R) -> Option<(Self::Move, Self)> {
+        self.legal_moves().choose(rng)
+    }
+}
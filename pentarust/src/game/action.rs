@@ -1,5 +1,13 @@
+use crate::game::Player;
 use crate::game::Swap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
+/// A move: placing a marble on a square, then swapping two quadrants
+///
+/// Actions are written `<column><row>/<swap>`, e.g. `c2/TL-BR`, where columns go from `a` to `f`
+/// left to right and rows go from `1` to `6` top to bottom.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Action {
     pub square: u8,
@@ -7,7 +15,207 @@ pub struct Action {
 }
 
 impl Action {
+    /// Number of different actions, see [`Action::index`]
+    pub const COUNT: usize = 36 * 6;
+
     pub fn new(square: u8, swap: Swap) -> Action {
         Action { square, swap }
     }
+
+    /// Returns a number below [`Action::COUNT`] identifying the action
+    pub fn index(self) -> usize {
+        6 * self.square as usize + self.swap.index()
+    }
+
+    /// Returns the action along with the player making it, for use with the Java server
+    pub fn transport(self, player: Player) -> TransportAction {
+        TransportAction {
+            action: self,
+            player,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let column = (b'a' + self.square % 6) as char;
+        let row = self.square / 6 + 1;
+        write!(f, "{}{}/{}", column, row, self.swap)
+    }
+}
+
+impl FromStr for Action {
+    type Err = ParseActionError;
+
+    fn from_str(s: &str) -> Result<Action, ParseActionError> {
+        let mut parts = s.split('/');
+        let (square, swap) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(square), Some(swap), None) => (square, swap),
+            _ => return Err(ParseActionError::InvalidFormat(s.to_owned())),
+        };
+
+        let mut chars = square.chars();
+        let (column, row) = match (chars.next(), chars.next(), chars.next()) {
+            (Some(column @ 'a'..='f'), Some(row @ '1'..='6'), None) => {
+                (column as u8 - b'a', row as u8 - b'1')
+            }
+            _ => return Err(ParseActionError::InvalidSquare(square.to_owned())),
+        };
+
+        Ok(Action::new(6 * row + column, swap.parse()?))
+    }
+}
+
+/// An action in the format of `PentagoMove::toTransportable`: `"x y TL TR playerId"`
+///
+/// `x` is the row and `y` is the column of the square, and the player id is 0 for player 1 (white)
+/// and 1 for player 2 (black).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TransportAction {
+    pub action: Action,
+    pub player: Player,
+}
+
+impl fmt::Display for TransportAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, b) = self.action.swap.quadrants();
+        let player_id = match self.player {
+            Player::Player1 => 0,
+            Player::Player2 => 1,
+        };
+
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.action.square / 6,
+            self.action.square % 6,
+            a,
+            b,
+            player_id,
+        )
+    }
+}
+
+impl FromStr for TransportAction {
+    type Err = ParseActionError;
+
+    fn from_str(s: &str) -> Result<TransportAction, ParseActionError> {
+        let parts: Vec<&str> = s.split(' ').collect();
+        if parts.len() != 5 {
+            return Err(ParseActionError::InvalidFormat(s.to_owned()));
+        }
+
+        let coordinate = |part: &str| match part.parse::<u8>() {
+            Ok(n) if n < 6 => Ok(n),
+            _ => Err(ParseActionError::InvalidSquare(format!("{} {}", parts[0], parts[1]))),
+        };
+        let x = coordinate(parts[0])?;
+        let y = coordinate(parts[1])?;
+
+        let a = parts[2].parse()?;
+        let b = parts[3].parse()?;
+        let swap = Swap::from_quadrants(a, b)
+            .ok_or_else(|| ParseActionError::InvalidSwap(format!("{} {}", parts[2], parts[3])))?;
+
+        let player = match parts[4] {
+            "0" => Player::Player1,
+            "1" => Player::Player2,
+            id => return Err(ParseActionError::InvalidPlayer(id.to_owned())),
+        };
+
+        Ok(Action::new(6 * x + y, swap).transport(player))
+    }
+}
+
+/// An error returned when parsing an action, a swap or a quadrant fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseActionError {
+    InvalidFormat(String),
+    InvalidSquare(String),
+    InvalidQuadrant(String),
+    InvalidSwap(String),
+    InvalidPlayer(String),
+}
+
+impl fmt::Display for ParseActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseActionError::InvalidFormat(s) => write!(f, "invalid action format: {:?}", s),
+            ParseActionError::InvalidSquare(s) => write!(f, "invalid square: {:?}", s),
+            ParseActionError::InvalidQuadrant(s) => write!(f, "invalid quadrant: {:?}", s),
+            ParseActionError::InvalidSwap(s) => write!(f, "invalid swap: {:?}", s),
+            ParseActionError::InvalidPlayer(s) => write!(f, "invalid player id: {:?}", s),
+        }
+    }
+}
+
+impl Error for ParseActionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_test() {
+        assert_eq!(Action::new(0, Swap::TL_TR).to_string(), "a1/TL-TR");
+        assert_eq!(Action::new(8, Swap::TL_BR).to_string(), "c2/TL-BR");
+        assert_eq!(Action::new(35, Swap::TR_BL).to_string(), "f6/TR-BL");
+    }
+
+    #[test]
+    fn index_test() {
+        let mut seen = vec![false; Action::COUNT];
+        for square in 0..36 {
+            for swap in Swap::iterator() {
+                let index = Action::new(square, *swap).index();
+                assert!(!seen[index]);
+                seen[index] = true;
+            }
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+
+    #[test]
+    fn from_str_test() {
+        assert_eq!("c2/TL-BR".parse(), Ok(Action::new(8, Swap::TL_BR)));
+        assert_eq!("f6/BL-TR".parse(), Ok(Action::new(35, Swap::TR_BL)));
+
+        assert_eq!(
+            "g1/TL-TR".parse::<Action>(),
+            Err(ParseActionError::InvalidSquare("g1".to_owned()))
+        );
+        assert_eq!(
+            "a1/TL-TL".parse::<Action>(),
+            Err(ParseActionError::InvalidSwap("TL-TL".to_owned()))
+        );
+        assert_eq!(
+            "a1/TL-XX".parse::<Action>(),
+            Err(ParseActionError::InvalidQuadrant("XX".to_owned()))
+        );
+        assert_eq!(
+            "a1 TL-TR".parse::<Action>(),
+            Err(ParseActionError::InvalidFormat("a1 TL-TR".to_owned()))
+        );
+    }
+
+    #[test]
+    fn transport_test() {
+        let action = Action::new(8, Swap::TL_BR).transport(Player::Player2);
+        assert_eq!(action.to_string(), "1 2 TL BR 1");
+        assert_eq!("1 2 TL BR 1".parse(), Ok(action));
+        assert_eq!("1 2 BR TL 1".parse(), Ok(action));
+
+        assert_eq!(
+            "1 6 TL BR 1".parse::<TransportAction>(),
+            Err(ParseActionError::InvalidSquare("1 6".to_owned()))
+        );
+        assert_eq!(
+            "1 2 TL BR 2".parse::<TransportAction>(),
+            Err(ParseActionError::InvalidPlayer("2".to_owned()))
+        );
+        assert_eq!(
+            "1 2 TL BR".parse::<TransportAction>(),
+            Err(ParseActionError::InvalidFormat("1 2 TL BR".to_owned()))
+        );
+    }
 }
@@ -2,10 +2,19 @@ use crate::game::board_symmetries::*;
 use crate::game::Action;
 use crate::game::Outcome;
 use crate::game::Player;
+use crate::game::RefereeRules;
+use crate::game::Rules;
 use crate::game::Swap;
+use crate::game::LINES;
+use crate::game::SWAPPED_LINES;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use std::error::Error;
 use std::fmt;
+use std::slice::Iter;
+use std::str::FromStr;
 
-const MASK: u64 = 0xF_FFFF_FFFF;
+pub(crate) const MASK: u64 = 0xF_FFFF_FFFF;
 
 #[derive(PartialEq, Eq, Default, Clone, Copy, PartialOrd, Ord, Hash)]
 pub struct Board {
@@ -15,6 +24,12 @@ pub struct Board {
 
 impl Board {
     /// Creates a new board from an array of integers. Useful for testing purposes.
+    ///
+    /// Squares hold 0 when empty, 1 for player 1 and 2 for player 2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a square holds any other value.
     pub fn new(array: [[u8; 6]; 6]) -> Board {
         let mut player1 = 0u64;
         let mut player2 = 0u64;
@@ -25,7 +40,8 @@ impl Board {
                 match array[i][j] {
                     1 => player1 |= 1 << (6 * i + j),
                     2 => player2 |= 1 << (6 * i + j),
-                    _ => {}
+                    0 => {}
+                    n => panic!("invalid value {} at row {}, column {}", n, i, j),
                 };
             }
         }
@@ -34,84 +50,113 @@ impl Board {
     }
 
     /// Returns the children that can arise from this board state
+    ///
+    /// Without filtering, a child is returned once for every action leading to it. With filtering,
+    /// each child is returned once, see [`Board::filtered_moves`].
     pub fn children(self, filter_forced_moves: bool) -> Vec<Board> {
-        let player = self.turn();
-        let mut free_squares = self.free_squares();
-        let mut children = Vec::<Board>::with_capacity((free_squares.count_ones() * 6) as usize);
-
-        let mut square = 0;
-        while free_squares != 0 {
-            if (free_squares & 1) != 0 {
-                for swap in Swap::iterator() {
-                    let child = self.play_at(player, square).swap(*swap);
-                    children.push(child);
-
-                    // Check if the opponent could win by playing this move
-                    if filter_forced_moves {
-                        let opponent = player.opponent();
-                        let is_opponent_winning_move = self
-                            .play_at(opponent, square)
-                            .swap(*swap)
-                            .player_won(opponent);
-
-                        if is_opponent_winning_move {
-                            let stone_placed = self.play_at(player, square);
-                            return Swap::iterator()
-                                .map(|swap| stone_placed.swap(*swap))
-                                .collect();
-                        }
-                    }
+        if filter_forced_moves {
+            self.filtered_moves()
+                .into_iter()
+                .map(|(_, child)| child)
+                .collect()
+        } else {
+            self.moves().map(|(_, child)| child).collect()
+        }
+    }
+
+    /// Returns an iterator over the actions that can be played from this board, along with the
+    /// boards they lead to. The moves are generated lazily, without allocating.
+    pub fn moves(self) -> Moves {
+        self.moves_for(self.turn())
+    }
+
+    /// Same as [`Board::moves`], for callers that already know whose turn it is
+    pub(crate) fn moves_for(self, player: Player) -> Moves {
+        Moves {
+            stone_placed: self,
+            player,
+            board: self,
+            free_squares: self.free_squares(),
+            square: 0,
+            swaps: [].iter(),
+        }
+    }
+
+    /// Returns an iterator over the moves that lead to distinct boards. When several actions lead
+    /// to the same board, which happens when two quadrants have the same contents, only one of
+    /// them is returned.
+    pub fn distinct_moves(self) -> DistinctMoves {
+        DistinctMoves::new(self, self.turn())
+    }
+
+    /// Returns a random action that can be played from this board, along with the board it leads to
+    ///
+    /// Every action is equally likely, even when several lead to the same board.
+    pub fn random_move<R: Rng>(self, rng: &mut R) -> Option<(Action, Board)> {
+        self.moves().choose(rng)
+    }
+
+    /// Returns the moves that lead to distinct boards. If the opponent could win by playing on a
+    /// square, only the moves that block that square are returned.
+    pub fn filtered_moves(self) -> Vec<(Action, Board)> {
+        self.filtered_moves_for(self.turn())
+    }
+
+    /// Same as [`Board::filtered_moves`], for callers that already know whose turn it is
+    pub(crate) fn filtered_moves_for(self, player: Player) -> Vec<(Action, Board)> {
+        let opponent = player.opponent();
+
+        // Check if the opponent could win by playing on a square
+        let opponent_winning_action = self.winning_actions(opponent).next();
+
+        if let Some(Action { square, .. }) = opponent_winning_action {
+            let stone_placed = self.play_at(player, square);
+            let mut moves: Vec<(Action, Board)> = Vec::with_capacity(6);
+
+            for swap in Swap::iterator() {
+                let child = stone_placed.swap(*swap);
+                if moves.iter().all(|(_, c)| *c != child) {
+                    moves.push((Action::new(square, *swap), child));
                 }
             }
 
-            free_squares >>= 1;
-            square += 1;
+            moves
+        } else {
+            DistinctMoves::new(self, player).collect()
         }
-
-        children
     }
 
     /// Returns the canonical equivalent fo this board.
     ///
     /// The canonical board is defined such that all equivalent board have the same canonical board.
     pub fn canonical(self) -> Board {
+        self.canonical_with_transform().0
+    }
+
+    /// Returns the canonical equivalent of this board, along with the symmetry that maps this board
+    /// to it. Use the inverse of the symmetry to map actions on the canonical board back to this one.
+    pub fn canonical_with_transform(self) -> (Board, Symmetry) {
         let vertical = flip_vertical(self);
         let symmetries = [
-            self,
-            vertical,
-            flip_horizontal(self),
-            flip_diagonal(self),
-            flip_antidiagonal(self),
-            flip_antidiagonal(vertical), // Rotate 90 clockwise
-            flip_horizontal(vertical),   // Rotate 180
-            flip_diagonal(vertical),     // Rotate 90 anticlockwise
+            (self, Symmetry::Identity),
+            (vertical, Symmetry::FlipVertical),
+            (flip_horizontal(self), Symmetry::FlipHorizontal),
+            (flip_diagonal(self), Symmetry::FlipDiagonal),
+            (flip_antidiagonal(self), Symmetry::FlipAntidiagonal),
+            (flip_antidiagonal(vertical), Symmetry::RotateClockwise),
+            (flip_horizontal(vertical), Symmetry::Rotate180),
+            (flip_diagonal(vertical), Symmetry::RotateAnticlockwise),
         ];
 
-        *symmetries.iter().min().unwrap()
+        *symmetries.iter().min_by_key(|(board, _)| *board).unwrap()
     }
 
     /// Returns the action that lead to the given state
     pub fn action_to(self, next_state: Board) -> Action {
-        let player = self.turn();
-        let mut free_squares = self.free_squares();
-
-        let mut square = 0;
-        while free_squares != 0 {
-            if (free_squares & 1) != 0 {
-                for swap in Swap::iterator() {
-                    let child = self.play_at(player, square).swap(*swap);
-
-                    if child == next_state {
-                        return Action::new(square, *swap);
-                    }
-                }
-            }
-
-            free_squares >>= 1;
-            square += 1;
-        }
-
-        panic!("Couldn't find action from {:?} to {:?}", self, next_state);
+        self.moves()
+            .find(|(_, child)| *child == next_state)
+            .map(|(action, _)| action)
+            .unwrap_or_else(|| panic!("Couldn't find action from {:?} to {:?}", self, next_state))
     }
 
     /// Applies an action to the current state
@@ -121,7 +166,7 @@ impl Board {
 
     /// Returns true of it's player 1's turn, false otherwise
     pub fn turn(&self) -> Player {
-        if (self.player1 | self.player2).count_ones() % 2 == 0 {
+        if (self.player1 | self.player2).count_ones() & 1 == 0 {
             Player::Player1
         } else {
             Player::Player2
@@ -132,60 +177,182 @@ impl Board {
         (self.player1 | self.player2).count_ones()
     }
 
+    /// Returns the outcome of the game under the rules of the Java referee
     pub fn outcome(self) -> Option<Outcome> {
-        if self.is_draw() {
-            Some(Outcome::Draw)
-        } else if self.player_won(Player::Player1) {
-            Some(Outcome::Player1Win)
-        } else if self.player_won(Player::Player2) {
-            Some(Outcome::Player2Win)
-        } else {
-            None
-        }
+        self.outcome_with(&RefereeRules)
+    }
+
+    /// Returns the outcome of the game under the given rules
+    pub fn outcome_with<R: Rules>(self, rules: &R) -> Option<Outcome> {
+        rules.outcome(self)
     }
 
     pub fn player_won(&self, player: Player) -> bool {
-        const WIDTH: u8 = 6;
-        let board = match player {
-            Player::Player1 => self.player1,
-            Player::Player2 => self.player2,
-        };
+        has_five(self.stones(player))
+    }
+
+    /// Returns the free squares on which the player would complete a five in a row, ignoring swaps
+    pub fn threat_squares(self, player: Player) -> u64 {
+        fours(self.stones(player)) & self.free_squares()
+    }
+
+    /// Returns the free squares after which a swap would complete a five in a row for the player
+    fn swap_threat_squares(self, player: Player) -> u64 {
+        let stones = self.stones(player);
+        let free_squares = self.free_squares();
+
+        SWAPPED_LINES
+            .iter()
+            .map(|line| line & !stones)
+            .filter(|missing| missing & free_squares != 0 && missing.count_ones() == 1)
+            .fold(0, |threats, missing| threats | missing)
+    }
 
-        // Check rows
-        let no_wrapping_board = board & 0b001111_001111_001111_001111_001111_001111;
-        let m = no_wrapping_board & (board >> 2);
-        if m & (m >> 1) & (m >> 2) != 0 {
-            return true;
+    /// Returns the number of lines on which the player has four marbles and the fifth square is free
+    pub fn open_fours(self, player: Player) -> u32 {
+        let stones = self.stones(player);
+        let free_squares = self.free_squares();
+
+        LINES
+            .iter()
+            .filter(|line| {
+                let missing = *line & !stones;
+                missing & free_squares != 0 && missing.count_ones() == 1
+            })
+            .count() as u32
+    }
+
+    /// Returns false if the player surely can't win with their next action
+    ///
+    /// This is faster than [`Board::winning_actions`], which should be used when it returns true:
+    /// it doesn't rule out the actions that also complete a five in a row for the opponent.
+    pub fn may_win(self, player: Player) -> bool {
+        // Four marbles and the one played make five
+        if self.stones(player).count_ones() < 4 {
+            return false;
         }
 
-        // Check columns
-        let m = board & board >> (2 * WIDTH);
-        if m & (m >> WIDTH) & (m >> (2 * WIDTH)) != 0 {
-            return true;
+        Swap::iterator().any(|swap| {
+            let swapped = self.swap(*swap);
+            swapped.player_won(player) || swapped.threat_squares(player) != 0
+        })
+    }
+
+    /// Returns the actions that would make the player win if it were their turn to play
+    ///
+    /// Actions that also complete a five in a row for the opponent are not included, since they
+    /// lead to a draw.
+    pub fn winning_actions(self, player: Player) -> WinningActions {
+        WinningActions {
+            board: self,
+            player,
+            swaps: Swap::iterator(),
+            swap: Swap::TL_TR,
+            squares: 0,
         }
+    }
+
+    /// Returns the squares where placing a marble before the swap makes the player win
+    fn winning_squares(self, player: Player, swap: Swap) -> u64 {
+        let swapped = self.swap(swap);
 
-        // Check diagonal 1
-        let no_wrapping_board = board & 0b000011_000111_001110_001110_111000_110000;
-        let m = no_wrapping_board & no_wrapping_board >> (2 * (WIDTH - 1));
-        if m & (m >> (WIDTH - 1)) & (m >> (2 * (WIDTH - 1))) != 0 {
-            return true;
+        // Placing a marble can't complete a line for the opponent
+        if swapped.player_won(player.opponent()) {
+            return 0;
         }
 
-        // Check diagonal 2
-        let no_wrapping_board = board & 0b110000_111000_011100_001110_000111_000011;
-        let m = no_wrapping_board & no_wrapping_board >> (2 * (WIDTH + 1));
-        if m & (m >> (WIDTH + 1)) & (m >> (2 * (WIDTH + 1))) != 0 {
-            return true;
+        let winning_squares = if swapped.player_won(player) {
+            swapped.free_squares()
+        } else {
+            swapped.threat_squares(player)
+        };
+
+        // Map the squares back to where the marble is placed before swapping
+        swap_u64(winning_squares, swap)
+    }
+
+    /// Returns the actions of the player after which the opponent can't win with their next move
+    ///
+    /// Actions that win or lead to a draw are included. Actions that lead to the same board may all
+    /// be returned.
+    pub fn defending_actions(self, player: Player) -> Vec<Action> {
+        let mut actions = Vec::new();
+
+        for swap in Swap::iterator() {
+            let mut squares = self.defending_squares(player, *swap);
+            while squares != 0 {
+                let square = squares.trailing_zeros() as u8;
+                squares &= squares - 1;
+                actions.push(Action::new(square, *swap));
+            }
         }
 
-        false
+        actions
+    }
+
+    /// Returns the squares where the player can place a marble before the given swap so that the
+    /// opponent can't win with their next move
+    pub fn defending_squares(self, player: Player, swap: Swap) -> u64 {
+        let opponent = player.opponent();
+
+        // Placing a marble then swapping gives the same board as swapping then placing the marble
+        // where the swap sends it, so the threats can be found once per swap
+        let swapped = self.swap(swap);
+
+        // Completing a five of our own ends the game before the opponent can play
+        let fives = || {
+            if swapped.player_won(player) {
+                swapped.free_squares()
+            } else {
+                swapped.threat_squares(player)
+            }
+        };
+
+        let squares = if swapped.player_won(opponent) {
+            fives()
+        } else {
+            let threats = Swap::iterator().fold(0, |threats, swap| {
+                threats | swapped.winning_squares(opponent, *swap)
+            });
+
+            // A marble blocks one square, so two threats can't be stopped
+            let mut squares = match threats.count_ones() {
+                0 => return swap_u64(swapped.free_squares(), swap),
+                1 => threats | fives(),
+                _ => fives(),
+            };
+
+            // A marble can also make every winning reply of the opponent complete a five of ours,
+            // which makes it a draw. This needs a line that a swap fills with our marbles.
+            let mut candidates = swapped.swap_threat_squares(player) & !squares;
+            while candidates != 0 {
+                let square = candidates.trailing_zeros() as u8;
+                candidates &= candidates - 1;
+
+                let child = swapped.play_at(player, square);
+                if child.winning_actions(opponent).next().is_none() {
+                    squares |= 1 << square;
+                }
+            }
+
+            squares
+        };
+
+        // Map the squares back to where the marble is placed before swapping
+        swap_u64(squares, swap)
     }
 
-    fn is_draw(self) -> bool {
+    /// Returns true if there are no free squares left
+    pub fn is_full(self) -> bool {
         self.player1 | self.player2 == MASK
     }
 
-    fn free_squares(self) -> u64 {
+    /// Returns true if the given square is on the board and has no marble on it
+    pub fn is_free(self, square: u8) -> bool {
+        square < 36 && self.free_squares() & (1 << square) != 0
+    }
+
+    pub(crate) fn free_squares(self) -> u64 {
         !(self.player1 | self.player2) & MASK
     }
 
@@ -202,40 +369,27 @@ impl Board {
     }
 
     fn swap(mut self, swap: Swap) -> Board {
-        const QUADRANT_MASK: u64 = 0b111_000_111_000_111;
-        const OFFSET_TOP_LEFT: u8 = 0;
-        const OFFSET_TOP_RIGHT: u8 = 3;
-        const OFFSET_BOTTOM_LEFT: u8 = 18;
-        const OFFSET_BOTTOM_RIGHT: u8 = 21;
-
-        let (offset1, offset2) = match swap {
-            Swap::TL_TR => (OFFSET_TOP_LEFT, OFFSET_TOP_RIGHT),
-            Swap::BL_BR => (OFFSET_BOTTOM_LEFT, OFFSET_BOTTOM_RIGHT),
-            Swap::TL_BL => (OFFSET_TOP_LEFT, OFFSET_BOTTOM_LEFT),
-            Swap::TR_BR => (OFFSET_TOP_RIGHT, OFFSET_BOTTOM_RIGHT),
-            Swap::TL_BR => (OFFSET_TOP_LEFT, OFFSET_BOTTOM_RIGHT),
-            Swap::TR_BL => (OFFSET_TOP_RIGHT, OFFSET_BOTTOM_LEFT),
-        };
-
-        let mask1 = QUADRANT_MASK << offset1;
-        let mask2 = QUADRANT_MASK << offset2;
-        let difference = offset2 - offset1;
-
-        // player 1
-        let tmp1 = self.player1 & mask1;
-        self.player1 &= !mask1;
-        self.player1 |= (self.player1 & mask2) >> difference;
-        self.player1 &= !mask2;
-        self.player1 |= tmp1 << difference;
+        self.player1 = swap_u64(self.player1, swap);
+        self.player2 = swap_u64(self.player2, swap);
+        self
+    }
 
-        // player 2
-        let tmp2 = self.player2 & mask1;
-        self.player2 &= !mask1;
-        self.player2 |= (self.player2 & mask2) >> difference;
-        self.player2 &= !mask2;
-        self.player2 |= tmp2 << difference;
+    pub(crate) fn stones(self, player: Player) -> u64 {
+        match player {
+            Player::Player1 => self.player1,
+            Player::Player2 => self.player2,
+        }
+    }
 
-        self
+    /// Returns the character for the square in the board's string format
+    fn square_char(self, square: u8) -> char {
+        if self.player1 & (1 << square) != 0 {
+            'x'
+        } else if self.player2 & (1 << square) != 0 {
+            'o'
+        } else {
+            '.'
+        }
     }
 
     /// Check if the board state is valid
@@ -249,6 +403,447 @@ impl Board {
     }
 }
 
+/// Iterator over the actions that make a player win, created by [`Board::winning_actions`]
+///
+/// The winning squares are found one swap at a time, so looking for a single winning action is
+/// cheap when there is one.
+pub struct WinningActions {
+    board: Board,
+    player: Player,
+    swaps: Iter<'static, Swap>,
+    /// The swap of the squares left
+    swap: Swap,
+    squares: u64,
+}
+
+impl Iterator for WinningActions {
+    type Item = Action;
+
+    fn next(&mut self) -> Option<Action> {
+        while self.squares == 0 {
+            self.swap = *self.swaps.next()?;
+            self.squares = self.board.winning_squares(self.player, self.swap);
+        }
+
+        let square = self.squares.trailing_zeros() as u8;
+        self.squares &= self.squares - 1;
+        Some(Action::new(square, self.swap))
+    }
+}
+
+/// Returns the squares that would complete a five in a row on the bitboard, occupied or not
+pub(crate) fn fours(board: u64) -> u64 {
+    // The shift to the next square of each direction, and the squares where a line of five starts
+    const DIRECTIONS: [(u32, u64); 4] = [
+        (1, 0b000011_000011_000011_000011_000011_000011),
+        (6, 0b111111_111111),
+        (7, 0b000011_000011),
+        (5, 0b110000_110000),
+    ];
+
+    let mut fours = 0;
+    for &(shift, starts) in DIRECTIONS.iter() {
+        // The lines, by their first square, on which each of the five squares is taken
+        let mut taken = [0; 5];
+        for (i, taken) in taken.iter_mut().enumerate() {
+            *taken = board >> (i as u32 * shift);
+        }
+
+        for missing in 0..5 {
+            let mut lines = starts;
+            for (i, taken) in taken.iter().enumerate() {
+                if i != missing {
+                    lines &= taken;
+                }
+            }
+            fours |= lines << (missing as u32 * shift);
+        }
+    }
+
+    fours
+}
+
+/// Returns true if the bitboard has five marbles in a row
+pub(crate) fn has_five(board: u64) -> bool {
+    const WIDTH: u8 = 6;
+
+    // Check rows
+    let no_wrapping_board = board & 0b001111_001111_001111_001111_001111_001111;
+    let m = no_wrapping_board & (board >> 2);
+    if m & (m >> 1) & (m >> 2) != 0 {
+        return true;
+    }
+
+    // Check columns
+    let m = board & board >> (2 * WIDTH);
+    if m & (m >> WIDTH) & (m >> (2 * WIDTH)) != 0 {
+        return true;
+    }
+
+    // Check diagonal 1
+    let no_wrapping_board = board & 0b000011_000111_001110_011100_111000_110000;
+    let m = no_wrapping_board & no_wrapping_board >> (2 * (WIDTH - 1));
+    if m & (m >> (WIDTH - 1)) & (m >> (2 * (WIDTH - 1))) != 0 {
+        return true;
+    }
+
+    // Check diagonal 2
+    let no_wrapping_board = board & 0b110000_111000_011100_001110_000111_000011;
+    let m = no_wrapping_board & no_wrapping_board >> (2 * (WIDTH + 1));
+    if m & (m >> (WIDTH + 1)) & (m >> (2 * (WIDTH + 1))) != 0 {
+        return true;
+    }
+
+    false
+}
+
+/// Exchanges the contents of two quadrants of a bitboard
+pub(crate) const fn swap_u64(mut x: u64, swap: Swap) -> u64 {
+    const QUADRANT_MASK: u64 = 0b111_000_111_000_111;
+    const OFFSET_TOP_LEFT: u8 = 0;
+    const OFFSET_TOP_RIGHT: u8 = 3;
+    const OFFSET_BOTTOM_LEFT: u8 = 18;
+    const OFFSET_BOTTOM_RIGHT: u8 = 21;
+
+    let (offset1, offset2) = match swap {
+        Swap::TL_TR => (OFFSET_TOP_LEFT, OFFSET_TOP_RIGHT),
+        Swap::BL_BR => (OFFSET_BOTTOM_LEFT, OFFSET_BOTTOM_RIGHT),
+        Swap::TL_BL => (OFFSET_TOP_LEFT, OFFSET_BOTTOM_LEFT),
+        Swap::TR_BR => (OFFSET_TOP_RIGHT, OFFSET_BOTTOM_RIGHT),
+        Swap::TL_BR => (OFFSET_TOP_LEFT, OFFSET_BOTTOM_RIGHT),
+        Swap::TR_BL => (OFFSET_TOP_RIGHT, OFFSET_BOTTOM_LEFT),
+    };
+
+    let mask1 = QUADRANT_MASK << offset1;
+    let mask2 = QUADRANT_MASK << offset2;
+    let difference = offset2 - offset1;
+
+    let tmp = x & mask1;
+    x &= !mask1;
+    x |= (x & mask2) >> difference;
+    x &= !mask2;
+    x |= tmp << difference;
+    x
+}
+
+/// Iterator over the moves that can be played from a board, created by [`Board::moves`]
+pub struct Moves {
+    board: Board,
+    player: Player,
+    free_squares: u64,
+    square: u8,
+    stone_placed: Board,
+    swaps: Iter<'static, Swap>,
+}
+
+impl Iterator for Moves {
+    type Item = (Action, Board);
+
+    fn next(&mut self) -> Option<(Action, Board)> {
+        let swap = match self.swaps.next() {
+            Some(swap) => *swap,
+            None => {
+                if self.free_squares == 0 {
+                    return None;
+                }
+
+                self.square = self.free_squares.trailing_zeros() as u8;
+                self.free_squares &= self.free_squares - 1;
+                self.stone_placed = self.board.play_at(self.player, self.square);
+                self.swaps = Swap::iterator();
+
+                *self.swaps.next().expect("there are six swaps")
+            }
+        };
+
+        let action = Action::new(self.square, swap);
+        Some((action, self.stone_placed.swap(swap)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.free_squares.count_ones() as usize * 6 + self.swaps.len();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Moves {}
+
+/// Iterator over the moves that lead to distinct boards, created by [`Board::distinct_moves`]
+///
+/// Placing a marble and then swapping two quadrants gives the same board as swapping first and
+/// placing the marble on the swapped square. The children are therefore generated by swapping
+/// first, which makes duplicates easy to find:
+///
+/// * Swaps that give the same board before placing the marble give the same children
+/// * Two different swapped boards give one common child when they only differ by one marble of the
+///   current player being on a different square
+pub struct DistinctMoves {
+    player: Player,
+    swapped: [Board; 6],
+    /// Number of swaps giving each swapped board, 0 if an earlier swap already gave it
+    multiplicity: [u8; 6],
+    /// Squares of each swapped board that give a child already given by an earlier swapped board
+    duplicates: [u64; 6],
+    index: usize,
+    free_squares: u64,
+}
+
+impl DistinctMoves {
+    pub(crate) fn new(board: Board, player: Player) -> DistinctMoves {
+        let mut swapped = [board; 6];
+        let mut multiplicity = [0; 6];
+        let mut duplicates = [0; 6];
+
+        for (i, swap) in Swap::iterator().enumerate() {
+            swapped[i] = board.swap(*swap);
+
+            match (0..i).find(|&j| multiplicity[j] != 0 && swapped[j] == swapped[i]) {
+                Some(j) => multiplicity[j] += 1,
+                None => {
+                    multiplicity[i] = 1;
+                    for j in (0..i).filter(|&j| multiplicity[j] != 0) {
+                        if let Some(square) = common_child(player, swapped[i], swapped[j]) {
+                            duplicates[i] |= square;
+                        }
+                    }
+                }
+            }
+        }
+
+        DistinctMoves {
+            player,
+            swapped,
+            multiplicity,
+            duplicates,
+            index: 0,
+            free_squares: swapped[0].free_squares(),
+        }
+    }
+
+    /// Returns an iterator that also yields how many actions lead to each board
+    pub fn with_counts(self) -> DistinctMovesWithCounts {
+        DistinctMovesWithCounts { moves: self }
+    }
+
+    fn next_square(&mut self) -> Option<(usize, u8)> {
+        while self.free_squares == 0 {
+            self.index += 1;
+            if self.index == 6 {
+                return None;
+            }
+
+            if self.multiplicity[self.index] != 0 {
+                self.free_squares =
+                    self.swapped[self.index].free_squares() & !self.duplicates[self.index];
+            }
+        }
+
+        let square = self.free_squares.trailing_zeros() as u8;
+        self.free_squares &= self.free_squares - 1;
+        Some((self.index, square))
+    }
+
+    fn action(&self, index: usize, square: u8) -> (Action, Board) {
+        let swap = *Swap::iterator().nth(index).expect("there are six swaps");
+        let action_square = swap_u64(1 << square, swap).trailing_zeros() as u8;
+        let child = self.swapped[index].play_at(self.player, square);
+
+        (Action::new(action_square, swap), child)
+    }
+
+    fn action_count(&self, index: usize, square: u8) -> u32 {
+        (0..6)
+            .filter(|&j| self.multiplicity[j] != 0)
+            .filter(|&j| {
+                j == index
+                    || common_child(self.player, self.swapped[index], self.swapped[j])
+                        == Some(1 << square)
+            })
+            .map(|j| u32::from(self.multiplicity[j]))
+            .sum()
+    }
+}
+
+/// If placing a marble on `a` and `b` can give the same board, returns the square of `a` on which
+/// the marble must be placed
+fn common_child(player: Player, a: Board, b: Board) -> Option<u64> {
+    let opponent = player.opponent();
+    let difference = a.stones(player) ^ b.stones(player);
+
+    if a.stones(opponent) == b.stones(opponent) && difference.count_ones() == 2 {
+        let square = difference & b.stones(player);
+        if square.count_ones() == 1 {
+            return Some(square);
+        }
+    }
+
+    None
+}
+
+impl Iterator for DistinctMoves {
+    type Item = (Action, Board);
+
+    fn next(&mut self) -> Option<(Action, Board)> {
+        self.next_square()
+            .map(|(index, square)| self.action(index, square))
+    }
+}
+
+/// Iterator over the moves that lead to distinct boards along with the number of actions leading
+/// to each board, created by [`DistinctMoves::with_counts`]
+pub struct DistinctMovesWithCounts {
+    moves: DistinctMoves,
+}
+
+impl Iterator for DistinctMovesWithCounts {
+    type Item = (Action, Board, u32);
+
+    fn next(&mut self) -> Option<(Action, Board, u32)> {
+        self.moves.next_square().map(|(index, square)| {
+            let (action, child) = self.moves.action(index, square);
+            (action, child, self.moves.action_count(index, square))
+        })
+    }
+}
+
+fn player_char(player: Player) -> char {
+    match player {
+        Player::Player1 => 'x',
+        Player::Player2 => 'o',
+    }
+}
+
+/// Writes the board as its 36 squares row by row, followed by the side to move. Player 1's stones
+/// are `x`, player 2's stones are `o` and empty squares are `.`:
+///
+/// ```text
+/// x......o............................ x
+/// ```
+///
+/// The alternate form (`{:#}`) draws a diagram with the quadrants and coordinates instead:
+///
+/// ```text
+///     a b c   d e f
+///   +-------+-------+
+/// 1 | x . . | . . . |
+/// 2 | . o . | . . . |
+/// 3 | . . . | . . . |
+///   +-------+-------+
+/// 4 | . . . | . . . |
+/// 5 | . . . | . . . |
+/// 6 | . . . | . . . |
+///   +-------+-------+
+/// x to move
+/// ```
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let turn = player_char(self.turn());
+
+        if !f.alternate() {
+            for square in 0..36 {
+                write!(f, "{}", self.square_char(square))?;
+            }
+            return write!(f, " {}", turn);
+        }
+
+        let separator = "  +-------+-------+";
+        writeln!(f, "    a b c   d e f")?;
+        for row in 0..6 {
+            if row % 3 == 0 {
+                writeln!(f, "{}", separator)?;
+            }
+
+            write!(f, "{} |", row + 1)?;
+            for column in 0..6 {
+                write!(f, " {}", self.square_char(6 * row + column))?;
+                if column % 3 == 2 {
+                    write!(f, " |")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "{}", separator)?;
+        write!(f, "{} to move", turn)
+    }
+}
+
+impl FromStr for Board {
+    type Err = ParseBoardError;
+
+    fn from_str(s: &str) -> Result<Board, ParseBoardError> {
+        let mut parts = s.split_whitespace();
+        let (squares, turn) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(squares), Some(turn), None) if squares.chars().count() == 36 => (squares, turn),
+            _ => return Err(ParseBoardError::InvalidFormat(s.to_owned())),
+        };
+
+        let mut board = Board::default();
+        for (square, c) in squares.chars().enumerate() {
+            match c {
+                'x' => board.player1 |= 1 << square,
+                'o' => board.player2 |= 1 << square,
+                '.' => {}
+                _ => return Err(ParseBoardError::InvalidSquare(c)),
+            }
+        }
+
+        let turn = match turn {
+            "x" => Player::Player1,
+            "o" => Player::Player2,
+            _ => return Err(ParseBoardError::InvalidTurn(turn.to_owned())),
+        };
+
+        let player1 = board.player1.count_ones();
+        let player2 = board.player2.count_ones();
+        if player1 != player2 && player1 != player2 + 1 {
+            return Err(ParseBoardError::WrongStoneCount { player1, player2 });
+        }
+
+        if turn != board.turn() {
+            return Err(ParseBoardError::WrongTurn(turn));
+        }
+
+        Ok(board)
+    }
+}
+
+/// An error returned when parsing a board fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseBoardError {
+    InvalidFormat(String),
+    InvalidSquare(char),
+    InvalidTurn(String),
+    /// Player 1 moves first, so they must have as many stones as player 2, or one more
+    WrongStoneCount {
+        player1: u32,
+        player2: u32,
+    },
+    /// The side to move doesn't match the number of stones on the board
+    WrongTurn(Player),
+}
+
+impl fmt::Display for ParseBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseBoardError::InvalidFormat(s) => write!(f, "invalid board format: {:?}", s),
+            ParseBoardError::InvalidSquare(c) => write!(f, "invalid square: {:?}", c),
+            ParseBoardError::InvalidTurn(s) => write!(f, "invalid side to move: {:?}", s),
+            ParseBoardError::WrongStoneCount { player1, player2 } => write!(
+                f,
+                "impossible stone count: {} for x and {} for o",
+                player1, player2
+            ),
+            ParseBoardError::WrongTurn(player) => write!(
+                f,
+                "{} can't be the side to move with these stones",
+                player_char(*player)
+            ),
+        }
+    }
+}
+
+impl Error for ParseBoardError {}
+
 impl fmt::Debug for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Board::new([")?;
@@ -297,6 +892,8 @@ impl fmt::Debug for Board {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
 
     #[test]
     fn new_test() {
@@ -354,6 +951,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn canonical_with_transform_test() {
+        let board = Board::new([
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 2, 0, 1],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+        let (canonical, symmetry) = board.canonical_with_transform();
+
+        assert_eq!(symmetry.apply(board), canonical);
+        for symmetry in Symmetry::iterator() {
+            assert_eq!(symmetry.apply(board).canonical(), canonical);
+        }
+
+        // A move found on the canonical board can be played on the real board
+        let action = Action::new(0, Swap::TL_BR);
+        let real_action = symmetry.inverse().apply_action(action);
+        assert_eq!(
+            board.apply_action(real_action),
+            symmetry.inverse().apply(canonical.apply_action(action))
+        );
+    }
+
     #[test]
     fn turn_test() {
         assert_eq!(
@@ -426,6 +1049,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn moves_test() {
+        let board = Board::new([
+            [1, 2, 1, 2, 1, 2],
+            [2, 1, 2, 1, 2, 1],
+            [1, 2, 1, 2, 1, 2],
+            [2, 1, 2, 1, 2, 1],
+            [1, 2, 0, 2, 1, 2],
+            [2, 1, 2, 1, 2, 0],
+        ]);
+        let moves = board.moves();
+        assert_eq!(moves.len(), 12);
+
+        let actions: Vec<Action> = moves.map(|(action, _)| action).collect();
+        assert_eq!(actions[0], Action::new(26, Swap::TL_TR));
+        assert_eq!(actions[11], Action::new(35, Swap::TR_BL));
+
+        for (action, child) in board.moves() {
+            assert_eq!(board.apply_action(action), child);
+            assert_eq!(board.apply_action(board.action_to(child)), child);
+        }
+
+        assert_eq!(Board::default().moves().count(), 216);
+    }
+
+    #[test]
+    fn distinct_moves_test() {
+        let boards = [
+            Board::default(),
+            Board::new([
+                [1, 0, 0, 1, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+            ]),
+            Board::new([
+                [1, 0, 0, 0, 1, 0],
+                [0, 2, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 2, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+            ]),
+            Board::new([
+                [1, 2, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 1, 2, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+            ]),
+        ];
+
+        for board in boards.iter() {
+            let mut expected: Vec<Board> = board.moves().map(|(_, child)| child).collect();
+            expected.sort_unstable();
+            expected.dedup();
+
+            let mut distinct: Vec<(Action, Board, u32)> =
+                board.distinct_moves().with_counts().collect();
+            for (action, child, count) in distinct.iter() {
+                assert_eq!(board.apply_action(*action), *child);
+                assert_eq!(
+                    *count as usize,
+                    board.moves().filter(|(_, c)| c == child).count()
+                );
+            }
+
+            distinct.sort_unstable_by_key(|(_, child, _)| *child);
+            let children: Vec<Board> = distinct.iter().map(|(_, child, _)| *child).collect();
+            assert_eq!(children, expected);
+        }
+
+        assert_eq!(Board::default().distinct_moves().count(), 36);
+    }
+
     #[test]
     fn swap_test() {
         assert_eq!(
@@ -491,7 +1192,7 @@ mod tests {
 
     #[test]
     fn player_won_test() {
-        assert_eq!(
+        assert!(
             Board::new([
                 [1, 1, 1, 1, 1, 0],
                 [0, 0, 0, 0, 0, 0],
@@ -500,11 +1201,10 @@ mod tests {
                 [0, 0, 0, 0, 0, 0],
                 [0, 0, 0, 0, 0, 0]
             ])
-            .player_won(Player::Player1),
-            true
+            .player_won(Player::Player1)
         );
 
-        assert_eq!(
+        assert!(
             Board::new([
                 [0, 0, 0, 0, 0, 0],
                 [0, 0, 2, 0, 0, 0],
@@ -513,11 +1213,10 @@ mod tests {
                 [0, 0, 2, 0, 0, 0],
                 [0, 0, 2, 0, 0, 0]
             ])
-            .player_won(Player::Player2),
-            true
+            .player_won(Player::Player2)
         );
 
-        assert_eq!(
+        assert!(
             Board::new([
                 [0, 1, 0, 0, 0, 0],
                 [0, 0, 1, 0, 0, 0],
@@ -526,11 +1225,10 @@ mod tests {
                 [0, 0, 0, 0, 0, 1],
                 [0, 0, 0, 0, 0, 0]
             ])
-            .player_won(Player::Player1),
-            true
+            .player_won(Player::Player1)
         );
 
-        assert_eq!(
+        assert!(
             Board::new([
                 [0, 0, 0, 0, 0, 2],
                 [0, 0, 0, 0, 2, 0],
@@ -539,15 +1237,75 @@ mod tests {
                 [0, 2, 0, 0, 0, 0],
                 [0, 0, 0, 0, 0, 0]
             ])
-            .player_won(Player::Player2),
-            true
+            .player_won(Player::Player2)
         );
     }
 
+    #[test]
+    fn threats_test() {
+        let board = Board::new([
+            [1, 1, 0, 1, 1, 0],
+            [0, 0, 0, 0, 0, 0],
+            [2, 2, 2, 2, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+
+        assert_eq!(board.threat_squares(Player::Player1), 1 << 2);
+        assert_eq!(board.threat_squares(Player::Player2), 1 << 16);
+        assert_eq!(board.open_fours(Player::Player1), 1);
+        assert_eq!(board.open_fours(Player::Player2), 1);
+
+        // The shifted masks find the same squares as the lines
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let stones = rng.gen::<u64>() & rng.gen::<u64>() & MASK;
+            let expected = LINES
+                .iter()
+                .map(|line| line & !stones)
+                .filter(|missing| missing.count_ones() == 1)
+                .fold(0, |fours, missing| fours | missing);
+            assert_eq!(fours(stones) & !stones, expected, "{:b}", stones);
+        }
+
+        for action in board.winning_actions(Player::Player1) {
+            let child = board
+                .play_at(Player::Player1, action.square)
+                .swap(action.swap);
+            assert_eq!(child.outcome(), Some(Outcome::Player1Win));
+        }
+        let wins = board
+            .moves()
+            .filter(|(_, child)| child.outcome() == Some(Outcome::Player1Win))
+            .count();
+        assert_eq!(board.winning_actions(Player::Player1).count(), wins);
+    }
+
+    #[test]
+    fn winning_actions_swap_test() {
+        // Swapping the top quadrants completes player 1's row wherever the marble goes
+        let board = Board::new([
+            [1, 1, 0, 1, 1, 1],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 2],
+            [0, 0, 0, 0, 0, 2],
+            [0, 0, 0, 0, 0, 2],
+        ]);
+
+        let actions: Vec<Action> = board
+            .winning_actions(Player::Player1)
+            .filter(|action| action.swap == Swap::TL_TR)
+            .collect();
+        assert_eq!(actions.len(), 28);
+        assert!(board.winning_actions(Player::Player2).next().is_none());
+    }
+
     #[test]
     fn player_won_wrapping() {
-        assert_eq!(
-            Board::new([
+        assert!(
+            !Board::new([
                 [0, 0, 0, 0, 0, 0],
                 [0, 0, 0, 1, 1, 1],
                 [1, 1, 0, 0, 0, 0],
@@ -555,12 +1313,11 @@ mod tests {
                 [0, 0, 0, 0, 0, 0],
                 [0, 0, 0, 0, 0, 0]
             ])
-            .player_won(Player::Player1),
-            false
+            .player_won(Player::Player1)
         );
 
-        assert_eq!(
-            Board::new([
+        assert!(
+            !Board::new([
                 [0, 0, 0, 1, 0, 0],
                 [0, 0, 0, 0, 1, 0],
                 [0, 0, 0, 0, 0, 1],
@@ -568,12 +1325,11 @@ mod tests {
                 [1, 0, 0, 0, 0, 0],
                 [0, 1, 0, 0, 0, 0]
             ])
-            .player_won(Player::Player1),
-            false
+            .player_won(Player::Player1)
         );
 
-        assert_eq!(
-            Board::new([
+        assert!(
+            !Board::new([
                 [0, 1, 0, 0, 0, 0],
                 [1, 0, 0, 0, 0, 1],
                 [0, 0, 0, 0, 1, 0],
@@ -581,8 +1337,149 @@ mod tests {
                 [0, 0, 0, 0, 0, 0],
                 [0, 0, 0, 0, 0, 0]
             ])
-            .player_won(Player::Player1),
-            false
+            .player_won(Player::Player1)
+        );
+    }
+
+    #[test]
+    fn may_win_test() {
+        let board: Board = "xxx...ooo..........x................ o".parse().unwrap();
+        assert!(!board.may_win(Player::Player2));
+
+        // The fourth marble can be swapped into the line
+        let board: Board = "xxx...ooo..........x.......o........ x".parse().unwrap();
+        assert!(board.may_win(Player::Player1));
+        assert!(board.winning_actions(Player::Player1).next().is_some());
+
+        for board in [
+            "xxxx..ooo........................... o",
+            "x.o...o..x....x..............o..x..o x",
+            "xxx.x.oo.o......x................o.. o",
+        ]
+        .iter()
+        {
+            let board: Board = board.parse().unwrap();
+            for player in [Player::Player1, Player::Player2].iter() {
+                if board.winning_actions(*player).next().is_some() {
+                    assert!(board.may_win(*player));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn defending_actions_test() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..1000 {
+            let stones = rng.gen_range(8, 34);
+            let mut board = Board::default();
+            while board.turn_number() < stones && board.outcome().is_none() {
+                board = board.random_move(&mut rng).unwrap().1;
+            }
+            if board.outcome().is_some() {
+                continue;
+            }
+
+            let player = board.turn();
+            let opponent_wins = |child: Board| match child.outcome() {
+                Some(outcome) => outcome == Outcome::win(player.opponent()),
+                None => child.winning_actions(player.opponent()).next().is_some(),
+            };
+
+            let mut defenses: Vec<Action> = board
+                .moves()
+                .filter(|(_, child)| !opponent_wins(*child))
+                .map(|(action, _)| action)
+                .collect();
+            let mut actions = board.defending_actions(player);
+
+            defenses.sort_by_key(|action| action.index());
+            actions.sort_by_key(|action| action.index());
+            assert_eq!(actions, defenses, "{}", board);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value 3 at row 0, column 1")]
+    fn new_invalid_test() {
+        Board::new([
+            [0, 3, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+    }
+
+    #[test]
+    fn string_test() {
+        let board = Board::new([
+            [1, 0, 0, 0, 0, 0],
+            [0, 2, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 1],
+        ]);
+        let s = "x......o...........................x o";
+
+        assert_eq!(board.to_string(), s);
+        assert_eq!(s.parse(), Ok(board));
+        assert_eq!(Board::default().to_string().parse(), Ok(Board::default()));
+    }
+
+    #[test]
+    fn diagram_test() {
+        let board: Board = "x......o............................ x".parse().unwrap();
+
+        assert_eq!(
+            format!("{:#}", board),
+            "    a b c   d e f\n\
+             \x20 +-------+-------+\n\
+             1 | x . . | . . . |\n\
+             2 | . o . | . . . |\n\
+             3 | . . . | . . . |\n\
+             \x20 +-------+-------+\n\
+             4 | . . . | . . . |\n\
+             5 | . . . | . . . |\n\
+             6 | . . . | . . . |\n\
+             \x20 +-------+-------+\n\
+             x to move"
+        );
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let empty = ".".repeat(36);
+
+        assert_eq!(
+            "x....".parse::<Board>(),
+            Err(ParseBoardError::InvalidFormat("x....".to_owned()))
+        );
+        assert_eq!(
+            empty.parse::<Board>(),
+            Err(ParseBoardError::InvalidFormat(empty.clone()))
+        );
+        assert_eq!(
+            format!("{}1 x", &empty[1..]).parse::<Board>(),
+            Err(ParseBoardError::InvalidSquare('1'))
+        );
+        assert_eq!(
+            format!("{} w", empty).parse::<Board>(),
+            Err(ParseBoardError::InvalidTurn("w".to_owned()))
+        );
+        assert_eq!(
+            format!("oo{} x", &empty[2..]).parse::<Board>(),
+            Err(ParseBoardError::WrongStoneCount {
+                player1: 0,
+                player2: 2
+            })
+        );
+        assert_eq!(
+            format!("x{} x", &empty[1..]).parse::<Board>(),
+            Err(ParseBoardError::WrongTurn(Player::Player1))
         );
     }
 }
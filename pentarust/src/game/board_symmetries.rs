@@ -1,4 +1,8 @@
+use crate::game::Action;
 use crate::game::Board;
+use crate::game::Quadrant;
+use crate::game::Swap;
+use std::slice::Iter;
 
 macro_rules! map_board {
     ($a:ident, $b:ident) => {
@@ -16,6 +20,90 @@ map_board!(flip_horizontal, flip_horizontal_u64);
 map_board!(flip_diagonal, flip_diagonal_u64);
 map_board!(flip_antidiagonal, flip_antidiagonal_u64);
 
+/// One of the eight symmetries of the board
+///
+/// Every symmetry maps quadrants to quadrants, so it commutes with swaps: applying a symmetry to
+/// a board and to an action gives the same result as applying the action and then the symmetry.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Symmetry {
+    Identity,
+    FlipVertical,
+    FlipHorizontal,
+    FlipDiagonal,
+    FlipAntidiagonal,
+    RotateClockwise,
+    Rotate180,
+    RotateAnticlockwise,
+}
+
+impl Symmetry {
+    pub fn iterator() -> Iter<'static, Symmetry> {
+        use self::Symmetry::*;
+        static SYMMETRIES: [Symmetry; 8] = [
+            Identity,
+            FlipVertical,
+            FlipHorizontal,
+            FlipDiagonal,
+            FlipAntidiagonal,
+            RotateClockwise,
+            Rotate180,
+            RotateAnticlockwise,
+        ];
+        SYMMETRIES.iter()
+    }
+
+    /// Returns the symmetry that undoes this one
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::RotateClockwise => Symmetry::RotateAnticlockwise,
+            Symmetry::RotateAnticlockwise => Symmetry::RotateClockwise,
+            symmetry => symmetry,
+        }
+    }
+
+    pub fn apply(self, board: Board) -> Board {
+        Board {
+            player1: self.apply_u64(board.player1),
+            player2: self.apply_u64(board.player2),
+        }
+    }
+
+    pub fn apply_square(self, square: u8) -> u8 {
+        self.apply_u64(1 << square).trailing_zeros() as u8
+    }
+
+    pub fn apply_quadrant(self, quadrant: Quadrant) -> Quadrant {
+        Quadrant::of_square(self.apply_square(quadrant.center()))
+    }
+
+    pub fn apply_swap(self, swap: Swap) -> Swap {
+        let (a, b) = swap.quadrants();
+        Swap::from_quadrants(self.apply_quadrant(a), self.apply_quadrant(b))
+            .expect("symmetries map distinct quadrants to distinct quadrants")
+    }
+
+    pub fn apply_action(self, action: Action) -> Action {
+        Action::new(
+            self.apply_square(action.square),
+            self.apply_swap(action.swap),
+        )
+    }
+
+    fn apply_u64(self, x: u64) -> u64 {
+        match self {
+            Symmetry::Identity => x,
+            Symmetry::FlipVertical => flip_vertical_u64(x),
+            Symmetry::FlipHorizontal => flip_horizontal_u64(x),
+            Symmetry::FlipDiagonal => flip_diagonal_u64(x),
+            Symmetry::FlipAntidiagonal => flip_antidiagonal_u64(x),
+            Symmetry::RotateClockwise => flip_antidiagonal_u64(flip_vertical_u64(x)),
+            Symmetry::Rotate180 => flip_horizontal_u64(flip_vertical_u64(x)),
+            Symmetry::RotateAnticlockwise => flip_diagonal_u64(flip_vertical_u64(x)),
+        }
+    }
+}
+
 fn flip_vertical_u64(mut x: u64) -> u64 {
     const K1: u64 = 0b000000_000000_111111_000000_000000_111111;
     const NOT_K1: u64 = !(K1 | (K1 << 12));
@@ -185,4 +273,70 @@ mod test {
             ])
         );
     }
+
+    #[test]
+    fn inverse_test() {
+        let board = Board::new([
+            [1, 1, 2, 0, 0, 0],
+            [0, 1, 0, 0, 0, 2],
+            [0, 0, 0, 0, 0, 0],
+            [0, 2, 0, 0, 0, 0],
+            [0, 0, 0, 0, 1, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+
+        for symmetry in Symmetry::iterator() {
+            assert_eq!(symmetry.inverse().apply(symmetry.apply(board)), board);
+
+            for square in 0..36 {
+                assert_eq!(
+                    symmetry
+                        .inverse()
+                        .apply_square(symmetry.apply_square(square)),
+                    square
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_clockwise_test() {
+        assert_eq!(
+            Symmetry::RotateClockwise.apply(*BOARD1),
+            Board::new([
+                [0, 0, 0, 0, 0, 1],
+                [0, 0, 0, 0, 1, 1],
+                [0, 0, 0, 1, 1, 1],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0]
+            ])
+        );
+        assert_eq!(
+            Symmetry::RotateClockwise.apply_swap(Swap::TL_BL),
+            Swap::TL_TR
+        );
+    }
+
+    #[test]
+    fn apply_action_test() {
+        let board = Board::new([
+            [1, 1, 2, 0, 0, 0],
+            [0, 1, 0, 0, 0, 2],
+            [0, 0, 0, 0, 0, 0],
+            [0, 2, 0, 0, 0, 0],
+            [0, 0, 0, 0, 1, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+
+        for symmetry in Symmetry::iterator() {
+            for (action, child) in board.moves() {
+                let transformed = symmetry.apply(board);
+                assert_eq!(
+                    transformed.apply_action(symmetry.apply_action(action)),
+                    symmetry.apply(child)
+                );
+            }
+        }
+    }
 }
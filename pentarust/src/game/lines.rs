@@ -0,0 +1,88 @@
+use crate::game::board::swap_u64;
+use crate::game::Swap;
+
+/// Masks of the 32 lines of five squares on which a player can win
+///
+/// There are 12 horizontal lines, 12 vertical lines and 8 diagonal lines.
+pub const LINES: [u64; 32] = lines();
+
+/// Masks of the squares that a swap moves onto each line, for every swap
+///
+/// Every action ends with a swap, so a player can only win with their next action if one of these
+/// is missing at most one of their marbles.
+pub const SWAPPED_LINES: [u64; 6 * 32] = swapped_lines();
+
+const fn lines() -> [u64; 32] {
+    const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+    let mut lines = [0; 32];
+    let mut n = 0;
+
+    let mut d = 0;
+    while d < DIRECTIONS.len() {
+        let (dr, dc) = DIRECTIONS[d];
+
+        let mut square = 0;
+        while square < 36 {
+            let (row, col) = (square / 6, square % 6);
+            let (end_row, end_col) = (row + 4 * dr, col + 4 * dc);
+
+            if end_row < 6 && end_col >= 0 && end_col < 6 {
+                let mut line = 0;
+                let mut i = 0;
+                while i < 5 {
+                    line |= 1 << (6 * (row + i * dr) + col + i * dc);
+                    i += 1;
+                }
+
+                lines[n] = line;
+                n += 1;
+            }
+
+            square += 1;
+        }
+
+        d += 1;
+    }
+
+    lines
+}
+
+const fn swapped_lines() -> [u64; 6 * 32] {
+    const SWAPS: [Swap; 6] = [
+        Swap::TL_TR,
+        Swap::BL_BR,
+        Swap::TL_BL,
+        Swap::TR_BR,
+        Swap::TL_BR,
+        Swap::TR_BL,
+    ];
+    let mut lines = [0; 6 * 32];
+
+    let mut i = 0;
+    while i < lines.len() {
+        lines[i] = swap_u64(LINES[i % 32], SWAPS[i / 32]);
+        i += 1;
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_test() {
+        assert_eq!(LINES[0], 0b011111);
+        assert_eq!(LINES[1], 0b111110);
+        assert!(LINES.iter().all(|line| line.count_ones() == 5));
+
+        for (i, a) in LINES.iter().enumerate() {
+            assert!(LINES[i + 1..].iter().all(|b| a != b));
+        }
+
+        assert!(SWAPPED_LINES.iter().all(|line| line.count_ones() == 5));
+        // Swapping the top left and bottom left quadrants moves the start of the first row down
+        assert_eq!(SWAPPED_LINES[32 * 2], 0b111 << 18 | 0b011_000);
+    }
+}
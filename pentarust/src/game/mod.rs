@@ -1,13 +1,29 @@
 mod action;
 mod board;
 mod board_symmetries;
+mod lines;
 mod outcome;
+mod perft;
 mod player;
+mod position;
+mod record;
+mod reference;
+mod rules;
+#[cfg(feature = "serde")]
+mod serialization;
 mod swap;
+mod zobrist;
 
 pub use action::*;
 pub use board::*;
 pub use board_symmetries::*;
+pub use lines::*;
 pub use outcome::*;
+pub use perft::*;
 pub use player::*;
+pub use position::*;
+pub use record::*;
+pub use reference::*;
+pub use rules::*;
 pub use swap::*;
+pub use zobrist::*;
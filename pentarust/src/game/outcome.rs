@@ -1,6 +1,7 @@
 use crate::game::Player;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Outcome {
     Player1Win,
     Player2Win,
@@ -0,0 +1,77 @@
+use crate::game::Board;
+use crate::game::Outcome;
+use std::collections::HashSet;
+use std::ops::AddAssign;
+
+/// Number of positions counted by [`perft`], and how many of them end the game
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerftCounts {
+    pub nodes: u64,
+    pub player1_wins: u64,
+    pub player2_wins: u64,
+    pub draws: u64,
+}
+
+impl PerftCounts {
+    fn count(&mut self, board: Board) {
+        self.nodes += 1;
+
+        match board.outcome() {
+            Some(Outcome::Player1Win) => self.player1_wins += 1,
+            Some(Outcome::Player2Win) => self.player2_wins += 1,
+            Some(Outcome::Draw) => self.draws += 1,
+            None => {}
+        }
+    }
+}
+
+impl AddAssign for PerftCounts {
+    fn add_assign(&mut self, other: PerftCounts) {
+        self.nodes += other.nodes;
+        self.player1_wins += other.player1_wins;
+        self.player2_wins += other.player2_wins;
+        self.draws += other.draws;
+    }
+}
+
+/// Counts the sequences of `depth` actions that can be played from a board, and the outcomes of
+/// the positions they lead to. Positions where the game is over are not searched further.
+///
+/// Every action is counted, even when several actions lead to the same position.
+pub fn perft(board: Board, depth: u32) -> PerftCounts {
+    let mut counts = PerftCounts::default();
+
+    if depth == 0 {
+        counts.count(board);
+    } else if board.outcome().is_none() {
+        for child in board.children(false) {
+            counts += perft(child, depth - 1);
+        }
+    }
+
+    counts
+}
+
+/// Counts the distinct positions, up to symmetry, reached after `depth` actions from a board, and
+/// their outcomes. Positions where the game is over are not searched further.
+pub fn perft_canonical(board: Board, depth: u32) -> PerftCounts {
+    let mut positions = HashSet::new();
+    positions.insert(board.canonical());
+
+    for _ in 0..depth {
+        positions = positions
+            .iter()
+            .filter(|position| position.outcome().is_none())
+            .flat_map(|position| position.distinct_moves())
+            .map(|(_, child)| child.canonical())
+            .collect();
+    }
+
+    let mut counts = PerftCounts::default();
+    for position in positions {
+        counts.count(position);
+    }
+
+    counts
+}
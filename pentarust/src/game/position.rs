@@ -0,0 +1,238 @@
+use crate::engine::Game;
+use crate::game::Action;
+use crate::game::Board;
+use crate::game::DistinctMoves;
+use crate::game::Outcome;
+use crate::game::Player;
+use crate::game::RefereeRules;
+use crate::game::Rules;
+use crate::game::ZobristHash;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+/// A board seen from the side of the player to move, for the search engines
+///
+/// The marbles are stored as the player to move's and the opponent's, so scores and patterns can be
+/// computed without checking whose turn it is. The ply and the Zobrist hash are kept up to date as
+/// moves are played, so they never have to be computed from scratch.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Position {
+    to_move: u64,
+    opponent: u64,
+    ply: u32,
+    hash: ZobristHash,
+}
+
+impl Position {
+    /// Returns the marbles of the player to move
+    pub fn to_move(self) -> u64 {
+        self.to_move
+    }
+
+    /// Returns the marbles of the player who just moved
+    pub fn opponent(self) -> u64 {
+        self.opponent
+    }
+
+    /// Returns the number of marbles on the board
+    pub fn ply(self) -> u32 {
+        self.ply
+    }
+
+    /// Returns the player whose turn it is
+    pub fn turn(self) -> Player {
+        if self.ply & 1 == 0 {
+            Player::Player1
+        } else {
+            Player::Player2
+        }
+    }
+
+    /// Returns the board, with the marbles stored by player
+    pub fn board(self) -> Board {
+        match self.turn() {
+            Player::Player1 => Board {
+                player1: self.to_move,
+                player2: self.opponent,
+            },
+            Player::Player2 => Board {
+                player1: self.opponent,
+                player2: self.to_move,
+            },
+        }
+    }
+
+    /// Returns the position reached by playing the given action
+    pub fn apply_action(self, action: Action) -> Position {
+        self.child(action, self.board().apply_action(action))
+    }
+
+    /// Returns the moves that lead to distinct positions, see [`Board::distinct_moves`]
+    pub fn distinct_moves(self) -> PositionMoves {
+        PositionMoves {
+            position: self,
+            moves: DistinctMoves::new(self.board(), self.turn()),
+        }
+    }
+
+    /// Returns the outcome of the game under the rules of the Java referee
+    pub fn outcome(self) -> Option<Outcome> {
+        RefereeRules.outcome(self)
+    }
+
+    /// Builds the child position from the board the action leads to
+    fn child(self, action: Action, child: Board) -> Position {
+        let player = self.turn();
+
+        Position {
+            to_move: child.stones(player.opponent()),
+            opponent: child.stones(player),
+            ply: self.ply + 1,
+            hash: self.hash.apply_action(player, action),
+        }
+    }
+}
+
+impl From<Board> for Position {
+    fn from(board: Board) -> Position {
+        let player = board.turn();
+
+        Position {
+            to_move: board.stones(player),
+            opponent: board.stones(player.opponent()),
+            ply: board.turn_number(),
+            hash: ZobristHash::new(board),
+        }
+    }
+}
+
+impl From<Position> for Board {
+    fn from(position: Position) -> Board {
+        position.board()
+    }
+}
+
+impl Game for Position {
+    type Move = Action;
+    type Moves = PositionMoves;
+    type Rules = RefereeRules;
+    const MOVE_COUNT: usize = Action::COUNT;
+
+    fn move_index(action: Action) -> usize {
+        action.index()
+    }
+
+    fn legal_moves(self) -> PositionMoves {
+        self.distinct_moves()
+    }
+
+    fn apply(self, action: Action) -> Position {
+        self.apply_action(action)
+    }
+
+    fn is_legal(self, action: Action) -> bool {
+        self.board().is_free(action.square)
+    }
+
+    fn turn(self) -> Player {
+        Position::turn(self)
+    }
+
+    fn ply(self) -> u32 {
+        self.ply
+    }
+
+    fn hash_key(self) -> u64 {
+        self.hash.key()
+    }
+
+    fn filtered_moves(self) -> Vec<(Action, Position)> {
+        self.board()
+            .filtered_moves_for(self.turn())
+            .into_iter()
+            .map(|(action, child)| (action, self.child(action, child)))
+            .collect()
+    }
+
+    fn random_move<R: Rng>(self, rng: &mut R) -> Option<(Action, Position)> {
+        self.board()
+            .moves_for(self.turn())
+            .choose(rng)
+            .map(|(action, child)| (action, self.child(action, child)))
+    }
+}
+
+/// Iterator over the moves that lead to distinct positions, created by
+/// [`Position::distinct_moves`]
+pub struct PositionMoves {
+    position: Position,
+    moves: DistinctMoves,
+}
+
+impl Iterator for PositionMoves {
+    type Item = (Action, Position);
+
+    fn next(&mut self) -> Option<(Action, Position)> {
+        self.moves
+            .next()
+            .map(|(action, child)| (action, self.position.child(action, child)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board() -> Board {
+        "x.o...o..x....x..............o..x..o x".parse().unwrap()
+    }
+
+    #[test]
+    fn conversion_test() {
+        let position = Position::from(board());
+
+        assert_eq!(position.turn(), Player::Player1);
+        assert_eq!(position.ply(), 8);
+        assert_eq!(position.to_move(), board().player1);
+        assert_eq!(position.opponent(), board().player2);
+        assert_eq!(Board::from(position), board());
+        assert_eq!(Position::from(Board::default()).hash_key(), 0);
+    }
+
+    #[test]
+    fn moves_test() {
+        let position = Position::from(board());
+        let moves: Vec<(Action, Position)> = position.legal_moves().collect();
+        let board_moves: Vec<(Action, Board)> = board().distinct_moves().collect();
+        assert_eq!(moves.len(), board_moves.len());
+
+        for ((action, child), (board_action, board_child)) in moves.iter().zip(board_moves.iter()) {
+            assert_eq!(action, board_action);
+            assert_eq!(*child, Position::from(*board_child));
+            assert_eq!(child.turn(), Player::Player2);
+
+            for (_, grandchild) in child.legal_moves() {
+                assert_eq!(grandchild, Position::from(grandchild.board()));
+            }
+        }
+    }
+
+    #[test]
+    fn outcome_test() {
+        let boards = [
+            "xxxxx.ooooo......................... x",
+            "xxxx..oooo....x...o................. x",
+            "xxxxxooo.o.......o.................. x",
+        ];
+
+        for board in boards.iter() {
+            let board: Board = board.parse().unwrap();
+            assert_eq!(
+                Position::from(board).outcome(),
+                board.outcome(),
+                "{}",
+                board
+            );
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use crate::game::Action;
+use crate::game::Board;
+use crate::game::Outcome;
+use crate::game::ParseActionError;
+use crate::game::ParseBoardError;
+use crate::game::Player;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A game being played, with its full history
+///
+/// Undone actions are kept until a different action is played, so they can be redone.
+///
+/// Games are written in a plain-text record format, with one line per turn and the result on the
+/// last line. A turn holds the action of player 1, then the action of player 2. A `[Start ...]` tag
+/// holding the board in the format of [`Board`]'s `Display` is added when the game doesn't start
+/// from the empty board, and when player 2 moves first, `...` stands for the missing action of
+/// player 1:
+///
+/// ```text
+/// 1. b2/BL-BR e2/TL-TR
+/// 2. b5/TL-BL
+/// *
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GameRecord {
+    actions: Vec<Action>,
+    boards: Vec<Board>,
+    ply: usize,
+}
+
+impl GameRecord {
+    /// Creates a game starting from the empty board
+    pub fn new() -> GameRecord {
+        GameRecord::from_position(Board::default())
+    }
+
+    /// Creates a game starting from the given position
+    pub fn from_position(start: Board) -> GameRecord {
+        GameRecord {
+            actions: Vec::new(),
+            boards: vec![start],
+            ply: 0,
+        }
+    }
+
+    /// Returns the position the game started from
+    pub fn start(&self) -> Board {
+        self.boards[0]
+    }
+
+    /// Returns the current position
+    pub fn board(&self) -> Board {
+        self.boards[self.ply]
+    }
+
+    /// Returns the number of actions played to reach the current position
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    /// Returns the actions played to reach the current position
+    pub fn actions(&self) -> &[Action] {
+        &self.actions[..self.ply]
+    }
+
+    /// Returns the position after the given number of actions, including undone actions
+    pub fn position_at(&self, ply: usize) -> Option<Board> {
+        self.boards.get(ply).cloned()
+    }
+
+    /// Returns the outcome of the game in the current position
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.board().outcome()
+    }
+
+    /// Plays an action from the current position, discarding any undone actions
+    pub fn play(&mut self, action: Action) -> Result<(), GameError> {
+        let board = self.board();
+
+        if board.outcome().is_some() {
+            return Err(GameError::GameOver);
+        }
+
+        if !board.is_free(action.square) {
+            return Err(GameError::IllegalAction(action));
+        }
+
+        self.actions.truncate(self.ply);
+        self.boards.truncate(self.ply + 1);
+        self.actions.push(action);
+        self.boards.push(board.apply_action(action));
+        self.ply += 1;
+
+        Ok(())
+    }
+
+    /// Takes back the last action, returning it
+    pub fn undo(&mut self) -> Option<Action> {
+        if self.ply == 0 {
+            return None;
+        }
+
+        self.ply -= 1;
+        Some(self.actions[self.ply])
+    }
+
+    /// Plays back the last undone action, returning it
+    pub fn redo(&mut self) -> Option<Action> {
+        if self.ply == self.actions.len() {
+            return None;
+        }
+
+        self.ply += 1;
+        Some(self.actions[self.ply - 1])
+    }
+
+    /// Moves to the position after the given number of actions, undoing or redoing as needed
+    pub fn replay_to(&mut self, ply: usize) -> Result<(), GameError> {
+        if ply > self.actions.len() {
+            return Err(GameError::PlyOutOfRange(ply));
+        }
+
+        self.ply = ply;
+        Ok(())
+    }
+}
+
+impl Default for GameRecord {
+    fn default() -> GameRecord {
+        GameRecord::new()
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start = self.start();
+        if start != Board::default() {
+            writeln!(f, "[Start \"{}\"]", start)?;
+        }
+
+        let mut actions = self.actions();
+        let mut turn = 1;
+        if start.turn() == Player::Player2 && !actions.is_empty() {
+            writeln!(f, "1. ... {}", actions[0])?;
+            actions = &actions[1..];
+            turn += 1;
+        }
+
+        for (i, actions) in actions.chunks(2).enumerate() {
+            write!(f, "{}.", turn + i)?;
+            for action in actions {
+                write!(f, " {}", action)?;
+            }
+            writeln!(f)?;
+        }
+
+        let result = match self.outcome() {
+            Some(Outcome::Player1Win) => "1-0",
+            Some(Outcome::Player2Win) => "0-1",
+            Some(Outcome::Draw) => "1/2-1/2",
+            None => "*",
+        };
+        writeln!(f, "{}", result)
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = ParseGameError;
+
+    fn from_str(s: &str) -> Result<GameRecord, ParseGameError> {
+        let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut line = lines.next().ok_or(ParseGameError::MissingResult)?;
+
+        let mut game = if line.starts_with("[Start ") {
+            let start = parse_start(line)?;
+            line = lines.next().ok_or(ParseGameError::MissingResult)?;
+            GameRecord::from_position(start)
+        } else {
+            GameRecord::new()
+        };
+
+        let mut turn = 1;
+        let mut result = None;
+        loop {
+            let invalid = || ParseGameError::InvalidLine(line.to_owned());
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+
+            // Nothing follows the result
+            if result.is_some() {
+                return Err(invalid());
+            }
+
+            if tokens[0] == format!("{}.", turn) {
+                // Player 2's action follows `...` on the first turn when it moves first
+                let (player, actions) = match tokens[1..] {
+                    ["...", ref actions @ ..] if turn == 1 => (Player::Player2, actions),
+                    ref actions => (Player::Player1, actions),
+                };
+
+                // A turn ends with the action of player 2, so only the last turn can be short
+                let turn_length = if player == Player::Player1 { 2 } else { 1 };
+                if game.board().turn() != player
+                    || actions.is_empty()
+                    || actions.len() > turn_length
+                {
+                    return Err(invalid());
+                }
+
+                for token in actions {
+                    game.play(token.parse()?)?;
+                }
+                turn += 1;
+            } else if tokens.len() == 1 {
+                result = Some(match tokens[0] {
+                    "1-0" => Some(Outcome::Player1Win),
+                    "0-1" => Some(Outcome::Player2Win),
+                    "1/2-1/2" => Some(Outcome::Draw),
+                    "*" => None,
+                    _ => return Err(invalid()),
+                });
+            } else {
+                return Err(invalid());
+            }
+
+            line = match lines.next() {
+                Some(line) => line,
+                None => break,
+            };
+        }
+
+        match result {
+            Some(outcome) if outcome == game.outcome() => Ok(game),
+            Some(_) => Err(ParseGameError::WrongResult),
+            None => Err(ParseGameError::MissingResult),
+        }
+    }
+}
+
+fn parse_start(line: &str) -> Result<Board, ParseGameError> {
+    let board = line
+        .strip_prefix("[Start \"")
+        .and_then(|line| line.strip_suffix("\"]"))
+        .ok_or_else(|| ParseGameError::InvalidLine(line.to_owned()))?;
+
+    // The board format checks the stone counts, so the record can't start from an impossible board
+    Ok(board.parse()?)
+}
+
+/// An error returned when an action can't be played
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameError {
+    GameOver,
+    IllegalAction(Action),
+    PlyOutOfRange(usize),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::GameOver => write!(f, "the game is over"),
+            GameError::IllegalAction(action) => write!(f, "illegal action: {}", action),
+            GameError::PlyOutOfRange(ply) => write!(f, "no position at ply {}", ply),
+        }
+    }
+}
+
+impl Error for GameError {}
+
+/// An error returned when parsing a game record fails
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseGameError {
+    InvalidLine(String),
+    InvalidStart(ParseBoardError),
+    InvalidAction(ParseActionError),
+    IllegalAction(GameError),
+    MissingResult,
+    WrongResult,
+}
+
+impl From<ParseBoardError> for ParseGameError {
+    fn from(error: ParseBoardError) -> ParseGameError {
+        ParseGameError::InvalidStart(error)
+    }
+}
+
+impl From<ParseActionError> for ParseGameError {
+    fn from(error: ParseActionError) -> ParseGameError {
+        ParseGameError::InvalidAction(error)
+    }
+}
+
+impl From<GameError> for ParseGameError {
+    fn from(error: GameError) -> ParseGameError {
+        ParseGameError::IllegalAction(error)
+    }
+}
+
+impl fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseGameError::InvalidLine(line) => write!(f, "invalid line: {:?}", line),
+            ParseGameError::InvalidStart(error) => write!(f, "{}", error),
+            ParseGameError::InvalidAction(error) => write!(f, "{}", error),
+            ParseGameError::IllegalAction(error) => write!(f, "{}", error),
+            ParseGameError::MissingResult => write!(f, "missing game result"),
+            ParseGameError::WrongResult => write!(f, "the result doesn't match the moves"),
+        }
+    }
+}
+
+impl Error for ParseGameError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Swap;
+
+    fn action(s: &str) -> Action {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn undo_redo_test() {
+        let mut game = GameRecord::new();
+        game.play(action("b2/BL-BR")).unwrap();
+        game.play(action("e2/TL-TR")).unwrap();
+        let board = game.board();
+
+        assert_eq!(game.undo(), Some(action("e2/TL-TR")));
+        assert_eq!(game.undo(), Some(action("b2/BL-BR")));
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.board(), Board::default());
+
+        assert_eq!(game.redo(), Some(action("b2/BL-BR")));
+        assert_eq!(game.redo(), Some(action("e2/TL-TR")));
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.board(), board);
+
+        game.replay_to(1).unwrap();
+        game.play(action("a1/TL-TR")).unwrap();
+        assert_eq!(game.actions(), &[action("b2/BL-BR"), action("a1/TL-TR")]);
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.replay_to(3), Err(GameError::PlyOutOfRange(3)));
+    }
+
+    #[test]
+    fn illegal_action_test() {
+        let mut game = GameRecord::new();
+        game.play(Action::new(0, Swap::BL_BR)).unwrap();
+
+        assert_eq!(
+            game.play(Action::new(0, Swap::BL_BR)),
+            Err(GameError::IllegalAction(Action::new(0, Swap::BL_BR)))
+        );
+    }
+
+    #[test]
+    fn record_test() {
+        let mut game = GameRecord::new();
+        for square in &[0, 6, 1, 7, 2, 8, 3, 9, 4] {
+            game.play(Action::new(*square, Swap::BL_BR)).unwrap();
+        }
+
+        let record = game.to_string();
+        assert_eq!(
+            record,
+            "1. a1/BL-BR a2/BL-BR\n\
+             2. b1/BL-BR b2/BL-BR\n\
+             3. c1/BL-BR c2/BL-BR\n\
+             4. d1/BL-BR d2/BL-BR\n\
+             5. e1/BL-BR\n\
+             1-0\n"
+        );
+        assert_eq!(record.parse(), Ok(game.clone()));
+
+        assert_eq!(
+            game.play(Action::new(5, Swap::BL_BR)),
+            Err(GameError::GameOver)
+        );
+    }
+
+    #[test]
+    fn record_start_test() {
+        let mut game = GameRecord::from_position(Board::new([
+            [1, 2, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]));
+        game.play(action("f6/TL-TR")).unwrap();
+
+        let record = game.to_string();
+        assert_eq!(
+            record,
+            "[Start \"xo.................................. x\"]\n1. f6/TL-TR\n*\n"
+        );
+        assert_eq!(record.parse(), Ok(game));
+
+        // Player 2 moves first
+        let mut game = GameRecord::from_position(Board::new([
+            [1, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]));
+        for a in &["f6/TL-TR", "b2/BL-BR", "e2/TL-TR"] {
+            game.play(action(a)).unwrap();
+        }
+
+        let record = game.to_string();
+        assert_eq!(
+            record,
+            "[Start \"x................................... o\"]\n\
+             1. ... f6/TL-TR\n\
+             2. b2/BL-BR e2/TL-TR\n\
+             *\n"
+        );
+        assert_eq!(record.parse(), Ok(game));
+
+        // The start must be a board reachable by playing
+        assert_eq!(
+            "[Start \"xxxxx............................... o\"]\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidStart(
+                ParseBoardError::WrongStoneCount {
+                    player1: 5,
+                    player2: 0
+                }
+            ))
+        );
+        assert_eq!(
+            "[Start \"1 0\"]\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidStart(
+                ParseBoardError::InvalidFormat("1 0".to_owned())
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert_eq!(
+            "1. b2/BL-BR\n1-0\n".parse::<GameRecord>(),
+            Err(ParseGameError::WrongResult)
+        );
+        assert_eq!(
+            "1. b2/BL-BR\n".parse::<GameRecord>(),
+            Err(ParseGameError::MissingResult)
+        );
+        assert_eq!(
+            "1. b2/BL-BR b2/BL-BR\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::IllegalAction(GameError::IllegalAction(
+                action("b2/BL-BR")
+            )))
+        );
+        assert_eq!(
+            "2. b2/BL-BR\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidLine("2. b2/BL-BR".to_owned()))
+        );
+
+        // Nothing follows the result
+        assert_eq!(
+            "1. b2/BL-BR\n*\n2. e2/TL-TR\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidLine("2. e2/TL-TR".to_owned()))
+        );
+        assert_eq!(
+            "*\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidLine("*".to_owned()))
+        );
+
+        // A turn holds one action of each player, and only the last one can be short
+        assert_eq!(
+            "1. a1/TL-TR b1/TL-TR c1/TL-TR\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidLine(
+                "1. a1/TL-TR b1/TL-TR c1/TL-TR".to_owned()
+            ))
+        );
+        assert_eq!(
+            "1.\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidLine("1.".to_owned()))
+        );
+        assert_eq!(
+            "1. b2/BL-BR\n2. e2/TL-TR\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidLine("2. e2/TL-TR".to_owned()))
+        );
+        assert_eq!(
+            "1. ... b2/BL-BR\n*\n".parse::<GameRecord>(),
+            Err(ParseGameError::InvalidLine("1. ... b2/BL-BR".to_owned()))
+        );
+    }
+}
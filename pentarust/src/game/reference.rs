@@ -0,0 +1,202 @@
+use crate::game::Action;
+use crate::game::Board;
+use crate::game::Outcome;
+use crate::game::Player;
+use crate::game::Quadrant;
+use crate::game::Swap;
+use crate::game::Symmetry;
+
+/// A slow, array-based implementation of the rules, written to be obviously correct
+///
+/// It is used to check the bitboard implementation of [`Board`], which relies on hand-made masks.
+/// Squares are indexed by row and column, like in [`Board::new`].
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub struct ReferenceBoard {
+    pub squares: [[Option<Player>; 6]; 6],
+}
+
+impl ReferenceBoard {
+    /// Converts a bitboard, which must be valid
+    pub fn from_board(board: Board) -> ReferenceBoard {
+        let mut reference = ReferenceBoard::default();
+
+        for row in 0..6 {
+            for column in 0..6 {
+                let bit = 1 << (6 * row + column);
+                reference.squares[row][column] = if board.player1 & bit != 0 {
+                    Some(Player::Player1)
+                } else if board.player2 & bit != 0 {
+                    Some(Player::Player2)
+                } else {
+                    None
+                };
+            }
+        }
+
+        reference
+    }
+
+    pub fn to_board(self) -> Board {
+        let mut board = Board::default();
+
+        for row in 0..6 {
+            for column in 0..6 {
+                let bit = 1 << (6 * row + column);
+                match self.squares[row][column] {
+                    Some(Player::Player1) => board.player1 |= bit,
+                    Some(Player::Player2) => board.player2 |= bit,
+                    None => {}
+                }
+            }
+        }
+
+        board
+    }
+
+    /// Returns true if the two bitboards describe a board: both fit in 36 squares, and no square
+    /// has two marbles
+    pub fn is_valid(player1: u64, player2: u64) -> bool {
+        (0..64).all(|bit| {
+            let player1_here = player1 & (1 << bit) != 0;
+            let player2_here = player2 & (1 << bit) != 0;
+
+            if bit < 36 {
+                !(player1_here && player2_here)
+            } else {
+                !player1_here && !player2_here
+            }
+        })
+    }
+
+    /// Returns the player whose turn it is, player 1 moving first
+    pub fn turn(self) -> Player {
+        let stones = self
+            .squares
+            .iter()
+            .flatten()
+            .filter(|s| s.is_some())
+            .count();
+
+        if stones % 2 == 0 {
+            Player::Player1
+        } else {
+            Player::Player2
+        }
+    }
+
+    /// Returns true if the player has five marbles in a row, horizontally, vertically or
+    /// diagonally
+    pub fn player_won(self, player: Player) -> bool {
+        let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+        for row in 0..6i32 {
+            for column in 0..6i32 {
+                for (dr, dc) in directions.iter() {
+                    let (end_row, end_column) = (row + 4 * dr, column + 4 * dc);
+                    if !(0..6).contains(&end_row) || !(0..6).contains(&end_column) {
+                        continue;
+                    }
+
+                    let five = (0..5).all(|k| {
+                        let r = (row + k * dr) as usize;
+                        let c = (column + k * dc) as usize;
+                        self.squares[r][c] == Some(player)
+                    });
+                    if five {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns the outcome under the rules of the Java referee, see [`RefereeRules`]
+    ///
+    /// [`RefereeRules`]: crate::game::RefereeRules
+    pub fn outcome(self) -> Option<Outcome> {
+        let player1_won = self.player_won(Player::Player1);
+        let player2_won = self.player_won(Player::Player2);
+        let full = self.squares.iter().flatten().all(|s| s.is_some());
+
+        if player1_won && player2_won {
+            Some(Outcome::Draw)
+        } else if player1_won {
+            Some(Outcome::Player1Win)
+        } else if player2_won {
+            Some(Outcome::Player2Win)
+        } else if full {
+            Some(Outcome::Draw)
+        } else {
+            None
+        }
+    }
+
+    /// Exchanges the contents of two quadrants, without rotating them
+    pub fn swap(mut self, swap: Swap) -> ReferenceBoard {
+        let (a, b) = swap.quadrants();
+        let (a_row, a_column) = corner(a);
+        let (b_row, b_column) = corner(b);
+
+        for i in 0..3 {
+            for j in 0..3 {
+                let tmp = self.squares[a_row + i][a_column + j];
+                self.squares[a_row + i][a_column + j] = self.squares[b_row + i][b_column + j];
+                self.squares[b_row + i][b_column + j] = tmp;
+            }
+        }
+
+        self
+    }
+
+    /// Places a marble for the player to move, then swaps the quadrants
+    pub fn apply_action(mut self, action: Action) -> ReferenceBoard {
+        let (row, column) = (action.square as usize / 6, action.square as usize % 6);
+        assert!(self.squares[row][column].is_none(), "the square is taken");
+
+        self.squares[row][column] = Some(self.turn());
+        self.swap(action.swap)
+    }
+
+    /// Moves every marble to the square the symmetry sends it to
+    pub fn apply_symmetry(self, symmetry: Symmetry) -> ReferenceBoard {
+        let mut result = ReferenceBoard::default();
+
+        for row in 0..6 {
+            for column in 0..6 {
+                let (new_row, new_column) = match symmetry {
+                    Symmetry::Identity => (row, column),
+                    Symmetry::FlipVertical => (5 - row, column),
+                    Symmetry::FlipHorizontal => (row, 5 - column),
+                    Symmetry::FlipDiagonal => (5 - column, 5 - row),
+                    Symmetry::FlipAntidiagonal => (column, row),
+                    Symmetry::RotateClockwise => (column, 5 - row),
+                    Symmetry::Rotate180 => (5 - row, 5 - column),
+                    Symmetry::RotateAnticlockwise => (5 - column, row),
+                };
+                result.squares[new_row][new_column] = self.squares[row][column];
+            }
+        }
+
+        result
+    }
+
+    /// Returns the smallest of the eight symmetric boards, like [`Board::canonical`]
+    pub fn canonical(self) -> Board {
+        Symmetry::iterator()
+            .map(|symmetry| self.apply_symmetry(*symmetry).to_board())
+            .min()
+            .unwrap()
+    }
+}
+
+/// Returns the row and column of the top left square of the quadrant
+fn corner(quadrant: Quadrant) -> (usize, usize) {
+    match quadrant {
+        Quadrant::TL => (0, 0),
+        Quadrant::TR => (0, 3),
+        Quadrant::BL => (3, 0),
+        Quadrant::BR => (3, 3),
+    }
+}
@@ -0,0 +1,142 @@
+use crate::game::board::has_five;
+use crate::game::board::MASK;
+use crate::game::Board;
+use crate::game::Outcome;
+use crate::game::Player;
+use crate::game::Position;
+
+/// Decides when a game is over and who won it
+///
+/// The engines score finished games with the rules of the game they search, see
+/// [`Game::Rules`](crate::engine::Game::Rules).
+pub trait Rules<G = Board> {
+    /// Returns the outcome of the game, or None if the game is still going
+    fn outcome(&self, state: G) -> Option<Outcome>;
+}
+
+/// The rules enforced by the Java referee (`PentagoBoardState::updateWinner`)
+///
+/// * If both players have five in a row, the game is a draw
+/// * If only one player has five in a row, that player wins, even if it was completed by the
+///   opponent's swap
+/// * Otherwise, a full board is a draw
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub struct RefereeRules;
+
+impl Rules for RefereeRules {
+    fn outcome(&self, board: Board) -> Option<Outcome> {
+        let player1_won = board.player_won(Player::Player1);
+        let player2_won = board.player_won(Player::Player2);
+
+        match (player1_won, player2_won) {
+            (true, true) => Some(Outcome::Draw),
+            (true, false) => Some(Outcome::Player1Win),
+            (false, true) => Some(Outcome::Player2Win),
+            (false, false) if board.is_full() => Some(Outcome::Draw),
+            (false, false) => None,
+        }
+    }
+}
+
+impl Rules<Position> for RefereeRules {
+    fn outcome(&self, position: Position) -> Option<Outcome> {
+        let to_move_won = has_five(position.to_move());
+        let opponent_won = has_five(position.opponent());
+        let player = position.turn();
+
+        match (to_move_won, opponent_won) {
+            (true, true) => Some(Outcome::Draw),
+            (true, false) => Some(Outcome::win(player)),
+            (false, true) => Some(Outcome::win(player.opponent())),
+            (false, false) if position.to_move() | position.opponent() == MASK => {
+                Some(Outcome::Draw)
+            }
+            (false, false) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_board_win_test() {
+        let board = Board::new([
+            [1, 1, 1, 1, 1, 2],
+            [2, 1, 2, 2, 2, 1],
+            [1, 1, 2, 1, 2, 2],
+            [2, 2, 1, 2, 1, 1],
+            [1, 2, 2, 1, 2, 2],
+            [2, 1, 1, 2, 1, 2],
+        ]);
+
+        assert_eq!(RefereeRules.outcome(board), Some(Outcome::Player1Win));
+    }
+
+    #[test]
+    fn full_board_draw_test() {
+        let board = Board::new([
+            [1, 1, 2, 2, 1, 1],
+            [2, 2, 1, 1, 2, 2],
+            [1, 1, 2, 2, 1, 1],
+            [2, 2, 1, 1, 2, 2],
+            [1, 1, 2, 2, 1, 1],
+            [2, 2, 1, 1, 2, 2],
+        ]);
+
+        assert_eq!(RefereeRules.outcome(board), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn double_win_test() {
+        let board = Board::new([
+            [1, 1, 1, 1, 1, 0],
+            [2, 2, 2, 2, 2, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+
+        assert_eq!(RefereeRules.outcome(board), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn opponent_win_test() {
+        // Player 1 just moved, but only player 2 has five in a row
+        let board = Board::new([
+            [1, 1, 1, 0, 0, 0],
+            [2, 2, 2, 2, 2, 0],
+            [1, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+
+        assert_eq!(RefereeRules.outcome(board), Some(Outcome::Player2Win));
+    }
+
+    #[test]
+    fn ongoing_test() {
+        assert_eq!(RefereeRules.outcome(Board::default()), None);
+    }
+
+    #[test]
+    fn position_test() {
+        // x filled the last square, completing a five for o with its swap
+        let board = Board::new([
+            [1, 1, 2, 2, 1, 1],
+            [2, 2, 2, 2, 2, 2],
+            [1, 2, 2, 1, 1, 2],
+            [1, 2, 1, 1, 2, 1],
+            [2, 1, 2, 2, 1, 1],
+            [1, 2, 1, 1, 2, 1],
+        ]);
+        let position = Position::from(board);
+
+        assert_eq!(RefereeRules.outcome(board), Some(Outcome::Player2Win));
+        assert_eq!(RefereeRules.outcome(position), RefereeRules.outcome(board));
+        assert_eq!(position.outcome(), board.outcome());
+    }
+}
@@ -0,0 +1,96 @@
+//! Serde support, enabled by the `serde` feature
+//!
+//! Types that have a text format are serialized as that text, so the serialized forms are readable
+//! and stay stable when the internal representation changes. Other types derive their
+//! implementations.
+
+use crate::game::Action;
+use crate::game::Board;
+use crate::game::GameRecord;
+use crate::game::Swap;
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+macro_rules! serde_with_str {
+    ($t:ty) => {
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<$t, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+serde_with_str!(Board);
+serde_with_str!(Action);
+serde_with_str!(Swap);
+serde_with_str!(GameRecord);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Outcome;
+    use crate::game::Player;
+    use crate::mcts::WinStats;
+
+    #[test]
+    fn board_test() {
+        let board: Board = "x......o............................ x".parse().unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+
+        assert_eq!(json, "\"x......o............................ x\"");
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+        assert!(serde_json::from_str::<Board>("\"x x\"").is_err());
+    }
+
+    #[test]
+    fn action_test() {
+        let action = Action::new(7, Swap::TL_BR);
+        let json = serde_json::to_string(&action).unwrap();
+
+        assert_eq!(json, "\"b2/TL-BR\"");
+        assert_eq!(serde_json::from_str::<Action>(&json).unwrap(), action);
+        assert_eq!(serde_json::to_string(&Swap::BL_BR).unwrap(), "\"BL-BR\"");
+    }
+
+    #[test]
+    fn game_test() {
+        let mut game = GameRecord::new();
+        game.play(Action::new(7, Swap::TL_BR)).unwrap();
+        let json = serde_json::to_string(&game).unwrap();
+
+        assert_eq!(json, "\"1. b2/TL-BR\\n*\\n\"");
+        assert_eq!(serde_json::from_str::<GameRecord>(&json).unwrap(), game);
+    }
+
+    #[test]
+    fn derived_test() {
+        assert_eq!(
+            serde_json::to_string(&Player::Player2).unwrap(),
+            "\"Player2\""
+        );
+        assert_eq!(serde_json::to_string(&Outcome::Draw).unwrap(), "\"Draw\"");
+
+        let stats = WinStats {
+            player1_wins: 2.,
+            player2_wins: 1.,
+            games_played: 4.,
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(
+            json,
+            "{\"player1_wins\":2.0,\"player2_wins\":1.0,\"games_played\":4.0}"
+        );
+        assert_eq!(serde_json::from_str::<WinStats>(&json).unwrap(), stats);
+    }
+}
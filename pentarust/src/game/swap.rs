@@ -1,5 +1,8 @@
 use self::Swap::*;
+use crate::game::ParseActionError;
+use std::fmt;
 use std::slice::Iter;
+use std::str::FromStr;
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -17,4 +20,111 @@ impl Swap {
         static SWAPS: [Swap; 6] = [TL_TR, BL_BR, TL_BL, TR_BR, TL_BR, TR_BL];
         SWAPS.iter()
     }
+
+    /// Returns the position of the swap in [`Swap::iterator`]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the two quadrants exchanged by this swap
+    pub fn quadrants(self) -> (Quadrant, Quadrant) {
+        match self {
+            TL_TR => (Quadrant::TL, Quadrant::TR),
+            BL_BR => (Quadrant::BL, Quadrant::BR),
+            TL_BL => (Quadrant::TL, Quadrant::BL),
+            TR_BR => (Quadrant::TR, Quadrant::BR),
+            TL_BR => (Quadrant::TL, Quadrant::BR),
+            TR_BL => (Quadrant::TR, Quadrant::BL),
+        }
+    }
+
+    /// Returns the swap that exchanges the two given quadrants, in any order
+    pub fn from_quadrants(a: Quadrant, b: Quadrant) -> Option<Swap> {
+        Swap::iterator()
+            .find(|swap| {
+                let quadrants = swap.quadrants();
+                quadrants == (a, b) || quadrants == (b, a)
+            })
+            .cloned()
+    }
+}
+
+impl fmt::Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, b) = self.quadrants();
+        write!(f, "{}-{}", a, b)
+    }
+}
+
+impl FromStr for Swap {
+    type Err = ParseActionError;
+
+    /// Parses a swap written as two quadrants separated by a dash, e.g. `TL-BR`
+    fn from_str(s: &str) -> Result<Swap, ParseActionError> {
+        let mut parts = s.split('-');
+        let (a, b) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(a), Some(b), None) => (a.parse()?, b.parse()?),
+            _ => return Err(ParseActionError::InvalidSwap(s.to_owned())),
+        };
+
+        Swap::from_quadrants(a, b).ok_or_else(|| ParseActionError::InvalidSwap(s.to_owned()))
+    }
+}
+
+/// A 3x3 quadrant of the board, named like `PentagoBoardState.Quadrant`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Quadrant {
+    TL,
+    TR,
+    BL,
+    BR,
+}
+
+impl Quadrant {
+    /// Returns the quadrant containing the given square
+    pub fn of_square(square: u8) -> Quadrant {
+        match (square / 6 < 3, square % 6 < 3) {
+            (true, true) => Quadrant::TL,
+            (true, false) => Quadrant::TR,
+            (false, true) => Quadrant::BL,
+            (false, false) => Quadrant::BR,
+        }
+    }
+
+    /// Returns the square at the center of the quadrant
+    pub fn center(self) -> u8 {
+        match self {
+            Quadrant::TL => 7,
+            Quadrant::TR => 10,
+            Quadrant::BL => 25,
+            Quadrant::BR => 28,
+        }
+    }
+}
+
+impl fmt::Display for Quadrant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Quadrant::TL => "TL",
+            Quadrant::TR => "TR",
+            Quadrant::BL => "BL",
+            Quadrant::BR => "BR",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Quadrant {
+    type Err = ParseActionError;
+
+    fn from_str(s: &str) -> Result<Quadrant, ParseActionError> {
+        match s {
+            "TL" => Ok(Quadrant::TL),
+            "TR" => Ok(Quadrant::TR),
+            "BL" => Ok(Quadrant::BL),
+            "BR" => Ok(Quadrant::BR),
+            _ => Err(ParseActionError::InvalidQuadrant(s.to_owned())),
+        }
+    }
 }
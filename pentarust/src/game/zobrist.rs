@@ -0,0 +1,164 @@
+use crate::game::Action;
+use crate::game::Board;
+use crate::game::Player;
+use crate::game::Swap;
+
+/// Hashes of every possible content of a quadrant, for each player
+///
+/// The content of a quadrant is given by 9 bits, one per square, read row by row.
+static QUADRANT_KEYS: [[u64; 512]; 2] = quadrant_keys();
+
+const QUADRANT_OFFSETS: [u8; 4] = [0, 3, 18, 21];
+
+/// Zobrist hash of a board, which can be updated incrementally
+///
+/// Each quadrant is hashed independently of its position, and the quadrant hashes are combined by
+/// rotating them depending on the position of the quadrant. Swapping two quadrants then only
+/// swaps their hashes.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, Hash)]
+pub struct ZobristHash {
+    quadrants: [u64; 4],
+}
+
+impl ZobristHash {
+    /// Hashes a board from scratch
+    pub fn new(board: Board) -> ZobristHash {
+        let mut quadrants = [0; 4];
+
+        for (hash, offset) in quadrants.iter_mut().zip(QUADRANT_OFFSETS.iter()) {
+            let player1 = quadrant_content(board.player1 >> offset);
+            let player2 = quadrant_content(board.player2 >> offset);
+            *hash = QUADRANT_KEYS[0][player1] ^ QUADRANT_KEYS[1][player2];
+        }
+
+        ZobristHash { quadrants }
+    }
+
+    /// Returns the 64 bit key of the board
+    pub fn key(self) -> u64 {
+        self.quadrants
+            .iter()
+            .enumerate()
+            .fold(0, |key, (i, hash)| key ^ hash.rotate_left(16 * i as u32))
+    }
+
+    /// Updates the hash after a marble is placed on a free square
+    pub fn play_at(mut self, player: Player, square: u8) -> ZobristHash {
+        let (row, col) = (square / 6, square % 6);
+        let quadrant = (row / 3 * 2 + col / 3) as usize;
+        let local_square = row % 3 * 3 + col % 3;
+
+        self.quadrants[quadrant] ^= QUADRANT_KEYS[player_index(player)][1 << local_square];
+        self
+    }
+
+    /// Updates the hash after two quadrants are swapped
+    pub fn swap(mut self, swap: Swap) -> ZobristHash {
+        let (a, b) = swap.quadrants();
+        self.quadrants.swap(a as usize, b as usize);
+        self
+    }
+
+    /// Updates the hash after the player plays the given action
+    pub fn apply_action(self, player: Player, action: Action) -> ZobristHash {
+        self.play_at(player, action.square).swap(action.swap)
+    }
+}
+
+fn player_index(player: Player) -> usize {
+    match player {
+        Player::Player1 => 0,
+        Player::Player2 => 1,
+    }
+}
+
+/// Packs the 9 squares of the top left quadrant of a bitboard into 9 bits
+fn quadrant_content(x: u64) -> usize {
+    ((x & 0o7) | ((x >> 3) & 0o70) | ((x >> 6) & 0o700)) as usize
+}
+
+const fn quadrant_keys() -> [[u64; 512]; 2] {
+    let mut keys = [[0; 512]; 2];
+    let mut state: u64 = 0x5EED_2019_0F9E_17A5;
+
+    let mut player = 0;
+    while player < 2 {
+        // One random number per square, then every content is the xor of its squares
+        let mut square = 0;
+        while square < 9 {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            keys[player][1 << square] = splitmix64(state);
+            square += 1;
+        }
+
+        let mut content: usize = 1;
+        while content < 512 {
+            let lowest = content & content.wrapping_neg();
+            keys[player][content] = keys[player][lowest] ^ keys[player][content ^ lowest];
+            content += 1;
+        }
+
+        player += 1;
+    }
+
+    keys
+}
+
+const fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incremental_test() {
+        let mut board = Board::default();
+        let mut hash = ZobristHash::new(board);
+
+        let actions = ["b2/BL-BR", "e2/TL-TR", "a1/TL-BR", "f6/TR-BL", "c4/TL-BL"];
+        for action in actions.iter() {
+            let action: Action = action.parse().unwrap();
+            hash = hash.apply_action(board.turn(), action);
+            board = board.apply_action(action);
+
+            assert_eq!(hash, ZobristHash::new(board));
+        }
+    }
+
+    #[test]
+    fn distinct_keys_test() {
+        let board = Board::new([
+            [1, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 0],
+        ]);
+        let mut keys: Vec<u64> = board
+            .moves()
+            .map(|(_, child)| ZobristHash::new(child).key())
+            .collect();
+        let mut children: Vec<Board> = board.moves().map(|(_, child)| child).collect();
+
+        keys.sort_unstable();
+        keys.dedup();
+        children.sort_unstable();
+        children.dedup();
+        assert_eq!(keys.len(), children.len());
+
+        assert_eq!(ZobristHash::new(Board::default()).key(), 0);
+        assert_ne!(
+            ZobristHash::new(board).key(),
+            ZobristHash::new(Board {
+                player1: 0,
+                player2: 1,
+            })
+            .key()
+        );
+    }
+}
@@ -1,19 +1,26 @@
-use crate::alpha_beta::search;
+use crate::alpha_beta::search_parallel;
 use crate::alpha_beta::TranspositionTable;
+use crate::alpha_beta::Weights;
 use crate::game::Action;
 use crate::game::Board;
 use crate::game::Player;
+use crate::game::Position;
 use crate::game::Swap;
 use jni::objects::JClass;
 use jni::JNIEnv;
 use lazy_static::lazy_static;
-use std::sync::Mutex;
+use std::env;
+use std::fs;
+use std::sync::Once;
+use std::thread;
 use std::time::Duration;
 
 // Todo: only export for the test target
 pub mod alpha_beta;
+pub mod engine;
 pub mod game;
 pub mod mcts;
+pub mod proof_number;
 
 const TOP_LEFT: u64 = 0;
 const TOP_RIGHT: u64 = 1;
@@ -40,7 +47,7 @@ pub extern "system" fn Java_student_1player_PentaRust_chooseMove(
     player1: u64,
     player2: u64,
 ) -> u64 {
-    choose_move(player1, player2)
+    choose_move(player1, player2, &PENTARUST_TABLE)
 }
 
 #[no_mangle]
@@ -51,14 +58,14 @@ pub extern "system" fn Java_student_1player_Baseline_chooseMove(
     player1: u64,
     player2: u64,
 ) -> u64 {
-    choose_move(player1, player2)
+    choose_move(player1, player2, &BASELINE_TABLE)
 }
 
-pub fn choose_move(player1: u64, player2: u64) -> u64 {
+pub fn choose_move(player1: u64, player2: u64, transpo_table: &TranspositionTable) -> u64 {
     let board = Board { player1, player2 };
-    let action = best_move(board, Duration::from_millis(1_800));
+    let action = best_move(board, Duration::from_millis(1_800), transpo_table);
 
-    trace!("{:?}", action);
+    trace!("{}", action);
 
     let x = u64::from(action.square % 6);
     let y = u64::from(action.square / 6);
@@ -106,12 +113,76 @@ pub fn best_move(board: Board, search_time: Duration) -> Action {
 */
 
 lazy_static! {
-    static ref TRANSPO_TABLE: Mutex<TranspositionTable> =
-        Mutex::new(TranspositionTable::new(1_000_000));
+    // One table per entry point, since both engines may play in the same process and each
+    // clears its own table when its game starts
+    static ref PENTARUST_TABLE: TranspositionTable = TranspositionTable::with_megabytes(32);
+    static ref BASELINE_TABLE: TranspositionTable = TranspositionTable::with_megabytes(32);
+
+    /// Number of threads searching, set by the `PENTARUST_THREADS` environment variable and
+    /// defaulting to the number of cores
+    static ref THREADS: usize = env::var("PENTARUST_THREADS")
+        .ok()
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
 }
 
-pub fn best_move(board: Board, search_time: Duration) -> Action {
-    let transpo_table = &mut TRANSPO_TABLE.lock().expect("failed to lock transpo table");
+static LOAD_WEIGHTS: Once = Once::new();
+
+/// Makes the search use the evaluation weights in the file named by the `PENTARUST_WEIGHTS`
+/// environment variable, such as one written by `opening-table tune`
+fn load_weights() {
+    let path = match env::var("PENTARUST_WEIGHTS") {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+
+    let weights = fs::read_to_string(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| text.parse::<Weights>().map_err(|error| error.to_string()));
+    match weights {
+        Ok(weights) => {
+            trace!("Loaded the weights from {}", path);
+            // The weights can't already be set, since this only runs once
+            let _ = weights.set_engine();
+        }
+        Err(error) => eprintln!("Couldn't load the weights from {}: {}", path, error),
+    }
+}
+
+pub fn best_move(
+    board: Board,
+    search_time: Duration,
+    transpo_table: &TranspositionTable,
+) -> Action {
+    LOAD_WEIGHTS.call_once(load_weights);
+
+    if board == Board::default() {
+        return Action::new(7, Swap::BL_BR);
+    }
+
+    // Each player searches for the first time with at most two marbles on the board
+    if board.turn_number() <= 2 {
+        transpo_table.new_game();
+    }
 
-    search(board, search_time, transpo_table)
+    let result = search_parallel(
+        Position::from(board),
+        search_time,
+        *THREADS,
+        transpo_table,
+        |_| {},
+    );
+    trace!(
+        "depth {} score {} proof {:?} nodes {} in {:?}, {:.1}% first move cutoffs",
+        result.depth,
+        result.score,
+        result.proof,
+        result.nodes,
+        result.elapsed,
+        result.first_move_cutoff_rate() * 100.
+    );
+    trace!("{:?}", result.principal_variation);
+    trace!("{:?}", transpo_table.stats());
+
+    result.best_move
 }
@@ -1,29 +1,38 @@
-use crate::game::Action;
-use crate::game::Board;
+use crate::engine::Game;
 use crate::game::Outcome;
+use crate::game::Position;
 use crate::mcts::WinStats;
 use float_ord::FloatOrd;
-use rand::seq::IteratorRandom;
 use rand::thread_rng;
 use rand::Rng;
 use std::time::{Duration, SystemTime};
 
 #[derive(Debug)]
-pub struct TreeNode {
-    pub state: Board,
-    pub children: Option<Vec<TreeNode>>,
+pub struct TreeNode<G: Game = Position> {
+    pub state: G,
+    /// The action that lead to this node, None for the root
+    pub action: Option<G::Move>,
+    pub children: Option<Vec<TreeNode<G>>>,
     pub win_stats: WinStats,
 }
 
-impl TreeNode {
-    pub fn new(state: Board) -> TreeNode {
+impl<G: Game> TreeNode<G> {
+    pub fn new(state: G) -> TreeNode<G> {
         TreeNode {
             state,
+            action: None,
             children: None,
             win_stats: Default::default(),
         }
     }
 
+    fn child(action: G::Move, state: G) -> TreeNode<G> {
+        TreeNode {
+            action: Some(action),
+            ..TreeNode::new(state)
+        }
+    }
+
     /// Repeatedly expands the tree until the maximum duration is reached
     pub fn search(&mut self, time_limit: Duration) {
         let start = SystemTime::now();
@@ -51,15 +60,24 @@ impl TreeNode {
                 .expect("Called TreeNode::expand() on a node with an empty list of children");
 
             next.expand(depth + 1)
+        } else if let Some(outcome) = self.state.outcome() {
+            // Terminal states are never expanded, but still count the games that reach them
+            outcome
         } else {
-            // Check if this is a terminal state
-            if let Some(outcome) = self.state.outcome() {
-                return outcome;
-            }
-
             // Only consider forced moves for nodes close to the root
-            let children = self.state.children(depth < 2);
-            self.children = Some(children.into_iter().map(TreeNode::new).collect());
+            let children = if depth < 2 {
+                self.state
+                    .filtered_moves()
+                    .into_iter()
+                    .map(|(action, child)| TreeNode::child(action, child))
+                    .collect()
+            } else {
+                self.state
+                    .legal_moves()
+                    .map(|(action, child)| TreeNode::child(action, child))
+                    .collect()
+            };
+            self.children = Some(children);
 
             self.simulate()
         };
@@ -79,17 +97,16 @@ impl TreeNode {
                 return outcome;
             }
 
-            state = *state
-                .children(false)
-                .iter()
-                .choose(&mut rng)
-                .expect("all non-terminal states have children");
+            state = state
+                .random_move(&mut rng)
+                .expect("all non-terminal states have children")
+                .1;
             player = player.opponent();
         }
     }
 
     /// Returns the best move from the perspective of the current player
-    pub fn best_move(&self) -> Action {
+    pub fn best_move(&self) -> G::Move {
         let player = self.state.turn();
         let best_child = self
             .children
@@ -99,6 +116,8 @@ impl TreeNode {
             .max_by_key(|child| FloatOrd(child.win_stats.expected_win_ratio(player)))
             .expect("Called TreeNode::best_move() on a node with no children");
 
-        self.state.action_to(best_child.state)
+        best_child
+            .action
+            .expect("Children of a node always have an action")
     }
 }
@@ -2,6 +2,7 @@ use crate::game::Outcome;
 use crate::game::Player;
 
 #[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WinStats {
     pub player1_wins: f32,
     pub player2_wins: f32,
@@ -0,0 +1,335 @@
+//! Exact solving of positions with depth-first proof-number search (df-pn)
+//!
+//! Proof-number search proves or disproves a single goal, such as "the player to move wins", by
+//! always expanding the part of the tree that is cheapest to settle. The game-theoretic value is
+//! found with two searches: one for a win, and if that fails, one for at least a draw.
+//!
+//! Symmetric positions share their proof and disproof numbers, since the table is keyed by
+//! [`Board::canonical`].
+
+use crate::game::Action;
+use crate::game::Board;
+use crate::game::Outcome;
+use crate::game::Player;
+use std::collections::HashMap;
+
+const INFINITY: u32 = u32::MAX;
+
+/// The game-theoretic value of a board, for the player to move
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Value {
+    Win,
+    Loss,
+    Draw,
+    /// The node budget ran out before the value was proven
+    Unknown,
+}
+
+/// The result of solving a board
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Solution {
+    pub value: Value,
+    /// A move that achieves the value, for wins and draws
+    pub best_move: Option<Action>,
+    /// Number of nodes expanded by the searches
+    pub nodes: u64,
+}
+
+/// Solves the board, expanding at most the given number of nodes
+///
+/// Without a budget, the search runs until the board is solved, which is only practical for boards
+/// close to the end of the game or with forced wins.
+pub fn solve(board: Board, budget: Option<u64>) -> Solution {
+    let player = board.turn();
+
+    if let Some(outcome) = board.outcome() {
+        let value = match outcome {
+            Outcome::Draw => Value::Draw,
+            _ if winner(outcome) == Some(player) => Value::Win,
+            _ => Value::Loss,
+        };
+        return Solution {
+            value,
+            best_move: None,
+            nodes: 0,
+        };
+    }
+
+    let mut budget = budget.unwrap_or(u64::MAX);
+    let mut nodes = 0;
+
+    for &(goal, value) in [(Goal::Win, Value::Win), (Goal::WinOrDraw, Value::Draw)].iter() {
+        let mut solver = Solver::new(player, goal, budget);
+        let proven = solver.prove(board);
+        nodes += solver.nodes;
+        budget -= solver.nodes.min(budget);
+
+        match proven {
+            Some(true) => {
+                return Solution {
+                    value,
+                    best_move: solver.proving_move(board),
+                    nodes,
+                }
+            }
+            Some(false) => (),
+            None => {
+                return Solution {
+                    value: Value::Unknown,
+                    best_move: None,
+                    nodes,
+                }
+            }
+        }
+    }
+
+    // The player to move can't even draw
+    Solution {
+        value: Value::Loss,
+        best_move: None,
+        nodes,
+    }
+}
+
+/// What the attacker of a search tries to prove it can achieve
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Goal {
+    Win,
+    WinOrDraw,
+}
+
+/// A df-pn search for one goal
+///
+/// The numbers are stored from the perspective of the player to move, as `(phi, delta)`: `phi` is
+/// the proof number of the player to move reaching its own goal, and `delta` the disproof number.
+/// The attacker's goal is its search goal, and the defender's goal is to stop the attacker.
+struct Solver {
+    attacker: Player,
+    goal: Goal,
+    table: HashMap<Board, (u32, u32)>,
+    nodes: u64,
+    budget: u64,
+}
+
+impl Solver {
+    fn new(attacker: Player, goal: Goal, budget: u64) -> Solver {
+        Solver {
+            attacker,
+            goal,
+            table: HashMap::new(),
+            nodes: 0,
+            budget,
+        }
+    }
+
+    /// Returns whether the attacker reaches its goal from the board, or None if the budget ran out
+    fn prove(&mut self, board: Board) -> Option<bool> {
+        let (phi, _) = self.mid(board, INFINITY, INFINITY)?;
+        Some((phi == 0) == (board.turn() == self.attacker))
+    }
+
+    /// Returns a move of the proven board that keeps the player to move on its goal
+    fn proving_move(&self, board: Board) -> Option<Action> {
+        let player = board.turn();
+        board.winning_actions(player).next().or_else(|| {
+            board
+                .distinct_moves()
+                .find(|&(_, child)| self.lookup(child.canonical()).1 == 0)
+                .map(|(action, _)| action)
+        })
+    }
+
+    /// Returns whether the outcome reaches the goal of the given player
+    fn reaches_goal(&self, player: Player, outcome: Outcome) -> bool {
+        let attacker_reaches_goal = match winner(outcome) {
+            Some(winner) => winner == self.attacker,
+            None => self.goal == Goal::WinOrDraw,
+        };
+        attacker_reaches_goal == (player == self.attacker)
+    }
+
+    /// Returns the numbers of a canonical board, which are 1 for boards that were never searched
+    fn lookup(&self, canonical: Board) -> (u32, u32) {
+        self.table.get(&canonical).cloned().unwrap_or((1, 1))
+    }
+
+    /// Searches the board until its `phi` or `delta` reaches its threshold, and returns them
+    ///
+    /// Returns None if the budget ran out.
+    fn mid(
+        &mut self,
+        board: Board,
+        phi_threshold: u32,
+        delta_threshold: u32,
+    ) -> Option<(u32, u32)> {
+        if self.nodes >= self.budget {
+            return None;
+        }
+        self.nodes += 1;
+
+        let key = board.canonical();
+        let player = board.turn();
+
+        if board.winning_actions(player).next().is_some() {
+            self.table.insert(key, (0, INFINITY));
+            return Some((0, INFINITY));
+        }
+
+        let mut children: Vec<Board> = board
+            .distinct_moves()
+            .map(|(_, child)| child.canonical())
+            .collect();
+        children.sort_unstable();
+        children.dedup();
+
+        // Finished games are settled once, and never searched
+        for &child in children.iter() {
+            if let Some(outcome) = child.outcome() {
+                let numbers = if self.reaches_goal(child.turn(), outcome) {
+                    (0, INFINITY)
+                } else {
+                    (INFINITY, 0)
+                };
+                self.table.insert(child, numbers);
+            }
+        }
+
+        loop {
+            // The player to move reaches its goal if it leads to any child where the opponent
+            // doesn't, and fails if the opponent reaches its goal in every child
+            let mut phi = INFINITY;
+            let mut delta: u32 = 0;
+            let mut best = None;
+            let mut second_delta = INFINITY;
+
+            for &child in children.iter() {
+                let (child_phi, child_delta) = self.lookup(child);
+                delta = delta.saturating_add(child_phi);
+
+                if child_delta < phi {
+                    second_delta = phi;
+                    phi = child_delta;
+                    best = Some((child, child_phi, child_delta));
+                } else if child_delta < second_delta {
+                    second_delta = child_delta;
+                }
+            }
+
+            self.table.insert(key, (phi, delta));
+            if phi >= phi_threshold || delta >= delta_threshold {
+                return Some((phi, delta));
+            }
+
+            let (child, child_phi, _) = best.expect("a board that isn't finished has moves");
+            let child_phi_threshold = delta_threshold - (delta - child_phi);
+            let child_delta_threshold = phi_threshold.min(second_delta.saturating_add(1));
+            self.mid(child, child_phi_threshold, child_delta_threshold)?;
+        }
+    }
+}
+
+/// Returns the player who won, or None for a draw
+fn winner(outcome: Outcome) -> Option<Player> {
+    match outcome {
+        Outcome::Player1Win => Some(Player::Player1),
+        Outcome::Player2Win => Some(Player::Player2),
+        Outcome::Draw => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Symmetry;
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    /// Returns the value of the board by searching the whole tree
+    fn brute_force(board: Board) -> Value {
+        let player = board.turn();
+        if let Some(outcome) = board.outcome() {
+            return match winner(outcome) {
+                Some(winner) if winner == player => Value::Win,
+                Some(_) => Value::Loss,
+                None => Value::Draw,
+            };
+        }
+
+        let mut value = Value::Loss;
+        for (_, child) in board.distinct_moves() {
+            match brute_force(child) {
+                Value::Loss => return Value::Win,
+                Value::Draw => value = Value::Draw,
+                _ => (),
+            }
+        }
+        value
+    }
+
+    fn opposite(value: Value) -> Value {
+        match value {
+            Value::Win => Value::Loss,
+            Value::Loss => Value::Win,
+            value => value,
+        }
+    }
+
+    #[test]
+    fn solve_test() {
+        // x completes the first row
+        let board: Board = "xxxx..oooo.......................... x".parse().unwrap();
+        let solution = solve(board, None);
+        assert_eq!(solution.value, Value::Win);
+        let action = solution.best_move.unwrap();
+        assert_eq!(
+            board.apply_action(action).outcome(),
+            Some(Outcome::Player1Win)
+        );
+
+        // o can't stop both rows
+        let board: Board = "xxxx..o.o.o..xxxx..o.o.o......o..... o".parse().unwrap();
+        let solution = solve(board, None);
+        assert_eq!(solution.value, Value::Loss);
+        assert_eq!(solution.best_move, None);
+
+        // The budget runs out long before the empty board is solved
+        let solution = solve(Board::default(), Some(100));
+        assert_eq!(solution.value, Value::Unknown);
+        assert!(solution.nodes <= 100);
+    }
+
+    #[test]
+    fn brute_force_test() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut values = Vec::new();
+
+        while values.len() < 20 {
+            let stones = rng.gen_range(31, 34);
+            let mut board = Board::default();
+            while board.turn_number() < stones && board.outcome().is_none() {
+                board = board.random_move(&mut rng).unwrap().1;
+            }
+            if board.outcome().is_some() {
+                continue;
+            }
+
+            let solution = solve(board, None);
+            assert_eq!(solution.value, brute_force(board), "{:?}", board);
+
+            // The proving move keeps the value, and symmetric boards have the same value
+            if let Some(action) = solution.best_move {
+                let child = board.apply_action(action);
+                assert_eq!(opposite(brute_force(child)), solution.value);
+            }
+            let flipped = Symmetry::RotateClockwise.apply(board);
+            assert_eq!(solve(flipped, None).value, solution.value);
+
+            values.push(solution.value);
+        }
+
+        // Losses are rare this late in random games, since swaps create so many threats
+        assert!(values.contains(&Value::Win));
+        assert!(values.contains(&Value::Draw));
+    }
+}
@@ -1,20 +1,21 @@
 use float_ord::FloatOrd;
 use pentarust::game::Board;
 use pentarust::game::Player;
+use pentarust::game::Position;
 use pentarust::mcts::TreeNode;
 use std::time::Duration;
 
 #[test]
 #[ignore]
 fn mcts_test() {
-    let mut tree = TreeNode::new(Board::new([
+    let mut tree = TreeNode::new(Position::from(Board::new([
         [0, 0, 0, 0, 0, 0],
         [0, 0, 0, 0, 0, 0],
         [0, 0, 0, 0, 0, 0],
         [0, 0, 0, 0, 0, 0],
         [0, 0, 0, 0, 0, 0],
         [0, 0, 0, 0, 0, 0],
-    ]));
+    ])));
     tree.search(Duration::from_secs(2));
 
     println!("Total Games: {}", tree.win_stats.games_played);
@@ -43,5 +44,5 @@ fn mcts_test() {
             .expect("non-empty list");
     }
     println!("Outcome: {:?}", cur.state.outcome());
-    println!("Final State: {:?}", cur.state);
+    println!("Final State: {:?}", cur.state.board());
 }
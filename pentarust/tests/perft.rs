@@ -0,0 +1,38 @@
+use pentarust::game::perft;
+use pentarust::game::perft_canonical;
+use pentarust::game::Board;
+use pentarust::game::PerftCounts;
+
+fn counts(numbers: &[u64]) -> PerftCounts {
+    PerftCounts {
+        nodes: numbers[0],
+        player1_wins: numbers[1],
+        player2_wins: numbers[2],
+        draws: numbers[3],
+    }
+}
+
+#[test]
+fn perft_test() {
+    let table = include_str!("perft.txt");
+
+    for line in table
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.is_empty())
+    {
+        let numbers: Vec<u64> = line.split(' ').map(|n| n.parse().unwrap()).collect();
+        let board = Board {
+            player1: numbers[0],
+            player2: numbers[1],
+        };
+        let depth = numbers[2] as u32;
+
+        assert_eq!(perft(board, depth), counts(&numbers[3..7]), "{}", line);
+        assert_eq!(
+            perft_canonical(board, depth),
+            counts(&numbers[7..11]),
+            "{}",
+            line
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Differential tests of the bitboard implementation against the naive reference implementation
+
+use pentarust::game::flip_antidiagonal;
+use pentarust::game::flip_diagonal;
+use pentarust::game::flip_horizontal;
+use pentarust::game::flip_vertical;
+use pentarust::game::Action;
+use pentarust::game::Board;
+use pentarust::game::Player;
+use pentarust::game::ReferenceBoard;
+use pentarust::game::Swap;
+use pentarust::game::Symmetry;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+const ITERATIONS: usize = 10_000;
+
+/// Returns a board where each square is taken with a random density, ignoring whose turn it is
+fn random_board(rng: &mut StdRng) -> Board {
+    let density: f64 = rng.gen();
+    let mut board = Board::default();
+
+    for square in 0..36 {
+        if rng.gen_bool(density) {
+            if rng.gen() {
+                board.player1 |= 1 << square;
+            } else {
+                board.player2 |= 1 << square;
+            }
+        }
+    }
+
+    board
+}
+
+/// Returns a board reached by playing random actions from the empty board
+fn random_game_board(rng: &mut StdRng) -> Board {
+    let mut board = Board::default();
+    let plies = rng.gen_range(0, 37);
+
+    for _ in 0..plies {
+        if board.outcome().is_some() {
+            break;
+        }
+
+        let free: Vec<u8> = (0..36).filter(|&s| board.is_free(s)).collect();
+        let square = free[rng.gen_range(0, free.len())];
+        let swap = Swap::iterator().nth(rng.gen_range(0, 6)).unwrap();
+        board = board.apply_action(Action::new(square, *swap));
+    }
+
+    board
+}
+
+fn boards() -> impl Iterator<Item = Board> {
+    let mut rng = StdRng::seed_from_u64(0x5EED);
+
+    (0..ITERATIONS).map(move |i| {
+        if i % 2 == 0 {
+            random_board(&mut rng)
+        } else {
+            random_game_board(&mut rng)
+        }
+    })
+}
+
+#[test]
+fn conversion_test() {
+    for board in boards() {
+        assert_eq!(ReferenceBoard::from_board(board).to_board(), board);
+    }
+}
+
+#[test]
+fn player_won_test() {
+    for board in boards() {
+        let reference = ReferenceBoard::from_board(board);
+
+        for player in &[Player::Player1, Player::Player2] {
+            assert_eq!(
+                board.player_won(*player),
+                reference.player_won(*player),
+                "{:?} {:?}",
+                player,
+                board
+            );
+        }
+        assert_eq!(board.outcome(), reference.outcome(), "{:?}", board);
+    }
+}
+
+#[test]
+fn apply_action_test() {
+    let mut rng = StdRng::seed_from_u64(0xAC7);
+
+    for board in boards().filter(|board| !board.is_full()) {
+        let reference = ReferenceBoard::from_board(board);
+        assert_eq!(board.turn(), reference.turn());
+
+        let free: Vec<u8> = (0..36).filter(|&s| board.is_free(s)).collect();
+        let square = free[rng.gen_range(0, free.len())];
+
+        for swap in Swap::iterator() {
+            let action = Action::new(square, *swap);
+            assert_eq!(
+                board.apply_action(action),
+                reference.apply_action(action).to_board(),
+                "{} {:?}",
+                action,
+                board
+            );
+        }
+    }
+}
+
+#[test]
+fn symmetries_test() {
+    for board in boards() {
+        let reference = ReferenceBoard::from_board(board);
+
+        for symmetry in Symmetry::iterator() {
+            assert_eq!(
+                symmetry.apply(board),
+                reference.apply_symmetry(*symmetry).to_board(),
+                "{:?} {:?}",
+                symmetry,
+                board
+            );
+        }
+
+        let flips = [
+            (flip_vertical(board), Symmetry::FlipVertical),
+            (flip_horizontal(board), Symmetry::FlipHorizontal),
+            (flip_diagonal(board), Symmetry::FlipDiagonal),
+            (flip_antidiagonal(board), Symmetry::FlipAntidiagonal),
+        ];
+        for (flipped, symmetry) in flips.iter() {
+            assert_eq!(*flipped, reference.apply_symmetry(*symmetry).to_board());
+        }
+    }
+}
+
+#[test]
+fn canonical_test() {
+    for board in boards() {
+        let canonical = board.canonical();
+        assert_eq!(canonical, ReferenceBoard::from_board(board).canonical());
+        assert_eq!(canonical.outcome(), board.outcome(), "{:?}", board);
+
+        for symmetry in Symmetry::iterator() {
+            assert_eq!(symmetry.apply(board).canonical(), canonical);
+        }
+
+        let (canonical, symmetry) = board.canonical_with_transform();
+        assert_eq!(symmetry.apply(board), canonical);
+    }
+}
+
+#[test]
+fn is_valid_test() {
+    let mut rng = StdRng::seed_from_u64(0x1D);
+
+    for _ in 0..ITERATIONS {
+        // Mostly stay within the board, with a few stray bits, so both answers are common
+        let mut player1: u64 = rng.gen::<u64>() & 0xF_FFFF_FFFF;
+        let mut player2: u64 = rng.gen::<u64>() & 0xF_FFFF_FFFF & !player1;
+        if rng.gen_bool(0.25) {
+            player1 |= 1 << rng.gen_range(0, 64);
+        }
+        if rng.gen_bool(0.25) {
+            player2 |= 1 << rng.gen_range(0, 64);
+        }
+
+        assert_eq!(
+            Board { player1, player2 }.is_valid(),
+            ReferenceBoard::is_valid(player1, player2),
+            "{} {}",
+            player1,
+            player2
+        );
+    }
+}
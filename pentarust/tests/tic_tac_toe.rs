@@ -0,0 +1,160 @@
+use pentarust::alpha_beta::negamax;
+use pentarust::alpha_beta::search;
+use pentarust::alpha_beta::Evaluate;
+use pentarust::alpha_beta::TranspositionTable;
+use pentarust::engine::Game;
+use pentarust::game::Outcome;
+use pentarust::game::Player;
+use pentarust::game::Rules;
+use pentarust::mcts::TreeNode;
+use std::time::Duration;
+use std::vec::IntoIter;
+
+const LINES: [u16; 8] = [
+    0b000_000_111,
+    0b000_111_000,
+    0b111_000_000,
+    0b001_001_001,
+    0b010_010_010,
+    0b100_100_100,
+    0b100_010_001,
+    0b001_010_100,
+];
+
+/// Tic-tac-toe, a game small enough that the engines must find the right answer
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+struct TicTacToe {
+    x: u16,
+    o: u16,
+}
+
+impl TicTacToe {
+    fn new(board: &str) -> TicTacToe {
+        let mut state = TicTacToe::default();
+        for (i, c) in board.chars().filter(|c| !c.is_whitespace()).enumerate() {
+            match c {
+                'x' => state.x |= 1 << i,
+                'o' => state.o |= 1 << i,
+                _ => {}
+            }
+        }
+        state
+    }
+}
+
+/// The first player with three in a row wins, and a full board is a draw
+#[derive(Debug, Default)]
+struct TicTacToeRules;
+
+impl Rules<TicTacToe> for TicTacToeRules {
+    fn outcome(&self, state: TicTacToe) -> Option<Outcome> {
+        if LINES.iter().any(|line| state.x & line == *line) {
+            Some(Outcome::Player1Win)
+        } else if LINES.iter().any(|line| state.o & line == *line) {
+            Some(Outcome::Player2Win)
+        } else if state.x | state.o == 0b111_111_111 {
+            Some(Outcome::Draw)
+        } else {
+            None
+        }
+    }
+}
+
+impl Game for TicTacToe {
+    type Move = u8;
+    type Moves = IntoIter<(u8, TicTacToe)>;
+    type Rules = TicTacToeRules;
+    const MOVE_COUNT: usize = 9;
+
+    fn move_index(square: u8) -> usize {
+        square as usize
+    }
+
+    fn legal_moves(self) -> IntoIter<(u8, TicTacToe)> {
+        let moves: Vec<(u8, TicTacToe)> = (0..9)
+            .filter(|square| (self.x | self.o) & (1 << square) == 0)
+            .map(|square| (square, self.apply(square)))
+            .collect();
+        moves.into_iter()
+    }
+
+    fn apply(mut self, square: u8) -> TicTacToe {
+        match self.turn() {
+            Player::Player1 => self.x |= 1 << square,
+            Player::Player2 => self.o |= 1 << square,
+        }
+        self
+    }
+
+    fn turn(self) -> Player {
+        if self.ply() & 1 == 0 {
+            Player::Player1
+        } else {
+            Player::Player2
+        }
+    }
+
+    fn ply(self) -> u32 {
+        (self.x | self.o).count_ones()
+    }
+
+    fn hash_key(self) -> u64 {
+        (u64::from(self.x) << 16) | u64::from(self.o)
+    }
+}
+
+impl Evaluate for TicTacToe {
+    fn eval(self) -> i32 {
+        0
+    }
+}
+
+fn negamax_full_depth(state: TicTacToe) -> i32 {
+    negamax(
+        state,
+        9,
+        -1_000_000,
+        1_000_000,
+        &TranspositionTable::new(1_000),
+        &|| false,
+    )
+    .unwrap()
+}
+
+#[test]
+fn negamax_draw_test() {
+    assert_eq!(negamax_full_depth(TicTacToe::default()), 0);
+}
+
+#[test]
+fn negamax_win_test() {
+    // x wins on its next move, at ply 5
+    let state = TicTacToe::new("xx. oo. ...");
+    assert_eq!(negamax_full_depth(state), 999_995);
+
+    // o plays in the corner, x makes two threats and wins at ply 7
+    let state = TicTacToe::new("x.. .o. ..x");
+    assert_eq!(negamax_full_depth(state.apply(2)), 999_993);
+}
+
+#[test]
+fn search_test() {
+    // o has to block x's column
+    let state = TicTacToe::new("x.. xo. ...");
+    let result = search(
+        state,
+        Duration::from_millis(50),
+        &TranspositionTable::new(1_000),
+    );
+    assert_eq!(result.best_move, 6);
+    assert_eq!(result.principal_variation[0], 6);
+}
+
+#[test]
+fn mcts_test() {
+    let state = TicTacToe::new("xx. oo. ...");
+    let mut tree = TreeNode::new(state);
+    tree.search(Duration::from_millis(50));
+
+    assert_eq!(tree.best_move(), 2);
+}